@@ -0,0 +1,138 @@
+use crate::error::*;
+use flate2::read::GzDecoder;
+use std::fs;
+use std::path::Path;
+use tracing::debug;
+
+const TAR_GZ_EXTENSIONS: [&str; 2] = [".tar.gz", ".tgz"];
+const ZIP_EXTENSIONS: [&str; 1] = [".zip"];
+
+/// is the path an archive (`.tar.gz`, `.tgz` or `.zip`) usable as template
+pub(crate) fn is_archive(path: &Path) -> bool {
+    let name = path.to_string_lossy().to_lowercase();
+    TAR_GZ_EXTENSIONS
+        .iter()
+        .chain(ZIP_EXTENSIONS.iter())
+        .any(|ext| name.ends_with(ext))
+}
+
+/// extract the archive into `dst`, if `dst` is missing or older than the archive (or if `force`)
+pub(crate) fn extract_if_outdated(archive: &Path, dst: &Path, force: bool) -> Result<()> {
+    let modified = |p: &Path| p.metadata().and_then(|m| m.modified()).ok();
+    let is_uptodate = match (modified(archive), modified(dst)) {
+        (Some(archive_time), Some(dst_time)) => dst_time >= archive_time,
+        _ => false,
+    };
+    if force || !is_uptodate {
+        extract(archive, dst)?;
+    }
+    Ok(())
+}
+
+/// extract the archive into `dst` (replace the previous content),
+/// a temporary folder is used so an interrupted extraction doesn't leave a partial template
+pub(crate) fn extract(archive: &Path, dst: &Path) -> Result<()> {
+    debug!(?archive, ?dst, "extract archive");
+    let parent = dst.parent().unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(parent).map_err(|source| Error::CreateFolder {
+        path: parent.to_path_buf(),
+        source,
+    })?;
+    let tmp_dir = tempfile::TempDir::with_prefix_in(crate::cache::EXTRACT_PREFIX, parent)
+        .map_err(|source| Error::CreateTmpFolder { source })?;
+    unpack(archive, tmp_dir.path()).map_err(|source| Error::ExtractArchive {
+        path: archive.to_path_buf(),
+        source,
+    })?;
+    // the extracted folder is moved to `dst` (if it's left, eg by a failure, `cache prune` removes it)
+    let tmp_path = tmp_dir.keep();
+    if dst.exists() {
+        fs::remove_dir_all(dst).map_err(|source| Error::RemoveFolder {
+            path: dst.to_path_buf(),
+            source,
+        })?;
+    }
+    fs::rename(&tmp_path, dst).map_err(|source| Error::CreateFolder {
+        path: dst.to_path_buf(),
+        source,
+    })?;
+    Ok(())
+}
+
+fn unpack(archive: &Path, dst: &Path) -> std::io::Result<()> {
+    let file = fs::File::open(archive)?;
+    let name = archive.to_string_lossy().to_lowercase();
+    if ZIP_EXTENSIONS.iter().any(|ext| name.ends_with(ext)) {
+        zip::ZipArchive::new(file)?.extract(dst)?;
+    } else {
+        tar::Archive::new(GzDecoder::new(file)).unpack(dst)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    #[rstest]
+    #[case("tmpl.tar.gz", true)]
+    #[case("tmpl.TGZ", true)]
+    #[case("path/to/tmpl.zip", true)]
+    #[case("tmpl.tar", false)]
+    #[case("tmpl.gz", false)]
+    #[case("path/to/tmpl", false)]
+    fn test_is_archive(#[case] path: &str, #[case] expected: bool) {
+        assert_eq!(is_archive(Path::new(path)), expected);
+    }
+
+    fn make_tar_gz(path: &Path) {
+        let mut builder = tar::Builder::new(GzEncoder::new(
+            fs::File::create(path).unwrap(),
+            Compression::default(),
+        ));
+        let content = b"hello\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "tmpl/file_1.txt", &content[..])
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    fn make_zip(path: &Path) {
+        let mut writer = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        writer
+            .start_file("tmpl/file_1.txt", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(b"hello\n").unwrap();
+        writer.finish().unwrap();
+    }
+
+    #[rstest]
+    #[case("tmpl.tar.gz", make_tar_gz)]
+    #[case("tmpl.zip", make_zip)]
+    fn test_extract(#[case] name: &str, #[case] make: fn(&Path)) {
+        let tmp_dir = tempdir().unwrap();
+        let archive = tmp_dir.path().join(name);
+        make(&archive);
+        let dst = tmp_dir.path().join("cache").join("tmpl");
+        fs::create_dir_all(&dst).unwrap();
+        fs::write(dst.join("previous.txt"), "previous\n").unwrap();
+
+        extract(&archive, &dst).unwrap();
+        assert_eq!(
+            fs::read_to_string(dst.join("tmpl/file_1.txt")).unwrap(),
+            "hello\n"
+        );
+        assert!(!dst.join("previous.txt").exists());
+        assert_eq!(fs::read_dir(dst.parent().unwrap()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,270 @@
+use crate::error::*;
+use crate::git;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::debug;
+use walkdir::WalkDir;
+
+/// folder of the cache with the remote templates (a repository per rev)
+pub(crate) const GIT_FOLDER: &str = "git";
+/// folder of the cache with the extracted archives
+pub(crate) const ARCHIVE_FOLDER: &str = "archive";
+/// prefix of the temporary folder of an extraction (left by an interrupted extraction)
+pub(crate) const EXTRACT_PREFIX: &str = ".extract";
+
+/// a template into the cache: a repository at a rev, or an extracted archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CacheEntry {
+    /// path relative to the cache folder (`git/host/path/rev` or `archive/name-digest`)
+    pub relative: PathBuf,
+    /// size in bytes
+    pub size: u64,
+    /// last fetch of the repository, or last extraction of the archive
+    pub fetched_at: Option<SystemTime>,
+    /// the commit checked out, none for an archive or if the repository is corrupted (eg interrupted clone)
+    pub commit: Option<String>,
+    pub archive: bool,
+    /// an interrupted clone or extraction
+    pub corrupted: bool,
+}
+
+impl CacheEntry {
+    pub fn is_corrupted(&self) -> bool {
+        self.corrupted
+    }
+}
+
+/// the cached templates: the repositories, the extracted archives,
+/// and the leftovers of an interrupted clone or extraction
+pub(crate) fn find_entries(cache_folder: &Path) -> Result<Vec<CacheEntry>> {
+    let mut back = find_repository_entries(cache_folder)?;
+    back.extend(find_archive_entries(cache_folder)?);
+    Ok(back)
+}
+
+fn find_archive_entries(cache_folder: &Path) -> Result<Vec<CacheEntry>> {
+    let mut back = vec![];
+    let archive_folder = cache_folder.join(ARCHIVE_FOLDER);
+    if !archive_folder.exists() {
+        return Ok(back);
+    }
+    for entry in WalkDir::new(&archive_folder)
+        .min_depth(1)
+        .max_depth(1)
+        .sort_by_file_name()
+    {
+        let entry = entry.map_err(std::io::Error::from)?;
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        let path = entry.path();
+        back.push(CacheEntry {
+            relative: path
+                .strip_prefix(cache_folder)
+                .unwrap_or(path)
+                .to_path_buf(),
+            size: find_size(path),
+            fetched_at: entry.metadata().ok().and_then(|m| m.modified().ok()),
+            commit: None,
+            archive: true,
+            corrupted: entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(EXTRACT_PREFIX),
+        });
+    }
+    Ok(back)
+}
+
+fn find_repository_entries(cache_folder: &Path) -> Result<Vec<CacheEntry>> {
+    let mut back = vec![];
+    let git_folder = cache_folder.join(GIT_FOLDER);
+    if !git_folder.exists() {
+        return Ok(back);
+    }
+    let mut it = WalkDir::new(&git_folder)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter();
+    while let Some(entry) = it.next() {
+        let entry = entry.map_err(std::io::Error::from)?;
+        let path = entry.path();
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        let is_repository = path.join(".git").exists();
+        if is_repository || fs::read_dir(path)?.next().is_none() {
+            it.skip_current_dir();
+            // without HEAD, git could find the commit of a parent repository
+            let commit = if path.join(".git").join("HEAD").exists() {
+                git::find_head_commit(path).ok()
+            } else {
+                None
+            };
+            back.push(CacheEntry {
+                relative: path
+                    .strip_prefix(cache_folder)
+                    .unwrap_or(path)
+                    .to_path_buf(),
+                size: find_size(path),
+                fetched_at: git::find_last_fetch_time(path),
+                corrupted: commit.is_none(),
+                commit,
+                archive: false,
+            });
+        }
+    }
+    Ok(back)
+}
+
+fn find_size(folder: &Path) -> u64 {
+    WalkDir::new(folder)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// remove the corrupted entries and the ones not fetched (or extracted) since `older_than`
+pub(crate) fn prune(cache_folder: &Path, older_than: Duration) -> Result<Vec<CacheEntry>> {
+    let now = SystemTime::now();
+    let removed = find_entries(cache_folder)?
+        .into_iter()
+        .filter(|entry| {
+            entry.is_corrupted()
+                || entry
+                    .fetched_at
+                    .and_then(|t| now.duration_since(t).ok())
+                    .is_none_or(|age| age > older_than)
+        })
+        .collect::<Vec<_>>();
+    for entry in &removed {
+        remove(cache_folder, &entry.relative)?;
+    }
+    Ok(removed)
+}
+
+/// remove the entries under the folder `relative` of the cache (every entries if empty)
+pub(crate) fn clear(cache_folder: &Path, relative: &Path) -> Result<Vec<CacheEntry>> {
+    let removed = find_entries(cache_folder)?
+        .into_iter()
+        .filter(|entry| entry.relative.starts_with(relative))
+        .collect::<Vec<_>>();
+    for entry in &removed {
+        remove(cache_folder, &entry.relative)?;
+    }
+    Ok(removed)
+}
+
+/// remove the folder `relative` of the cache (and its parents when they become empty)
+pub(crate) fn remove(cache_folder: &Path, relative: &Path) -> Result<()> {
+    let path = cache_folder.join(relative);
+    if path.exists() {
+        debug!(?path, "remove from cache");
+        fs::remove_dir_all(&path).map_err(|source| Error::RemoveFolder {
+            path: path.clone(),
+            source,
+        })?;
+    }
+    for parent in path.ancestors().skip(1) {
+        if parent == cache_folder || !parent.starts_with(cache_folder) {
+            break;
+        }
+        if fs::read_dir(parent).map_or(true, |mut d| d.next().is_some()) {
+            break;
+        }
+        fs::remove_dir(parent).map_err(|source| Error::RemoveFolder {
+            path: parent.to_path_buf(),
+            source,
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(all(test, not(target_os = "windows")))]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use tempfile::tempdir;
+
+    fn init_repository(path: &Path) {
+        fs::create_dir_all(path).unwrap();
+        let (code, output, error) = run_script::run(
+            &format!(
+                r#"
+                    cd {path:?}
+                    git init -q -b master
+                    git config user.email "test@example.com"
+                    git config user.name "Test Name"
+                    echo "v1" > foo.txt
+                    git add foo.txt
+                    git commit -q -m "v1"
+                    "#
+            ),
+            &vec![],
+            &run_script::ScriptOptions::new(),
+        )
+        .unwrap();
+        assert_eq!(code, 0, "init repository: {output} {error}");
+    }
+
+    #[test]
+    fn test_find_prune_remove_entries() {
+        let tmp_dir = tempdir().unwrap();
+        let cache = tmp_dir.path().join("cache");
+        init_repository(&cache.join("git/github.com/ffizer/foo/master"));
+        init_repository(&cache.join("git/github.com/ffizer/foo/v1.0.0"));
+        // interrupted clones
+        fs::create_dir_all(cache.join("git/github.com/ffizer/bar/master")).unwrap();
+        fs::create_dir_all(cache.join("git/github.com/ffizer/baz/master/.git")).unwrap();
+        // extracted archive, and interrupted extraction
+        fs::create_dir_all(cache.join("archive/tmpl.zip-0a1b/tmpl")).unwrap();
+        fs::write(
+            cache.join("archive/tmpl.zip-0a1b/tmpl/file_1.txt"),
+            "hello\n",
+        )
+        .unwrap();
+        fs::create_dir_all(cache.join("archive/.extractXYZ/tmpl")).unwrap();
+
+        let entries = find_entries(&cache).unwrap();
+        let summary = entries
+            .iter()
+            .map(|e| (e.relative.to_string_lossy().to_string(), e.is_corrupted()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("git/github.com/ffizer/bar/master".to_owned(), true),
+                ("git/github.com/ffizer/baz/master".to_owned(), true),
+                ("git/github.com/ffizer/foo/master".to_owned(), false),
+                ("git/github.com/ffizer/foo/v1.0.0".to_owned(), false),
+                ("archive/.extractXYZ".to_owned(), true),
+                ("archive/tmpl.zip-0a1b".to_owned(), false),
+            ]
+        );
+        assert!(entries[2].size > 0);
+        assert_eq!(entries[2].commit.as_ref().map(|c| c.len()), Some(40));
+        assert!(entries[5].archive);
+        assert_eq!(entries[5].size, 6);
+
+        let removed = prune(&cache, Duration::from_secs(3600)).unwrap();
+        assert_eq!(removed.len(), 3);
+        assert!(!cache.join("git/github.com/ffizer/bar").exists());
+        assert!(!cache.join("git/github.com/ffizer/baz").exists());
+        assert!(!cache.join("archive/.extractXYZ").exists());
+        assert_eq!(find_entries(&cache).unwrap().len(), 3);
+
+        remove(&cache, Path::new("git/github.com/ffizer/foo/master")).unwrap();
+        assert_eq!(find_entries(&cache).unwrap().len(), 2);
+        let removed = clear(&cache, Path::new("git/github.com/ffizer/foo")).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert!(!cache.join("git").exists());
+        let removed = clear(&cache, Path::new("")).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert!(!cache.join("archive").exists());
+        assert!(cache.exists());
+    }
+}
@@ -1,13 +1,37 @@
+use super::ignore_cfg::IgnoreCfg;
 use super::transform_values::TransformsValues;
+use super::variable_cfg::{VariableCfg, VariableValueCfg};
 use crate::Result;
 use schemars::JsonSchema;
+use std::collections::BTreeMap;
 
 #[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, JsonSchema)]
 /// define a template layer to import
 pub(crate) struct ImportCfg {
+    /// uri / path of the template (could include the subfolder and the rev as `uri//subfolder@rev`)
     pub uri: String,
     pub rev: Option<String>,
     pub subfolder: Option<String>,
+    /// overrides of the variables defined by the imported template (and by its imports)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variables: Vec<VariableOverrideCfg>,
+    /// store the answers of the imported template under this name (eg `backend` for `backend.name`)
+    pub namespace: Option<String>,
+    /// values of variables of the imported template (not asked) from an expression of the importing template
+    /// (eg `version: "{{ app_version }}"`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub map: BTreeMap<String, String>,
+    /// condition to import the template (eg `"{{ with_docker }}"`), the import is skipped if it's rendered as `false` or empty
+    #[serde(rename = "if")]
+    pub condition: Option<String>,
+    /// folder (relative to the destination) where the imported template is applied (eg `crates/{{ name }}`)
+    pub dst_subfolder: Option<String>,
+    /// list of path from the imported template (and from its imports) to ignore
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignores: Vec<IgnoreCfg>,
+    /// path generated by the imported template -> new path (eg `README.md: docs/base.md`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rename: BTreeMap<String, String>,
 }
 
 impl TransformsValues for ImportCfg {
@@ -23,6 +47,87 @@ impl TransformsValues for ImportCfg {
             uri,
             rev,
             subfolder,
+            // rendered later, like the variables of the template
+            variables: self.variables.clone(),
+            namespace: self.namespace.clone(),
+            map: self.map.clone(),
+            // evaluated when the variables are defined (see `TemplateComposite::from_src`)
+            condition: self.condition.clone(),
+            // rendered with the variables of the importing layer (see `LayerScope`)
+            dst_subfolder: self.dst_subfolder.clone(),
+            // rendered with the other ignores of the imported layer
+            ignores: self.ignores.clone(),
+            rename: self.rename.clone(),
         })
     }
 }
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, Eq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+/// override the definition of a variable of an imported template
+pub(crate) struct VariableOverrideCfg {
+    /// name of the variable to override
+    pub name: String,
+    /// new default value
+    pub default_value: Option<VariableValueCfg>,
+    /// new sentence to ask the value
+    pub ask: Option<String>,
+    /// new condition to hide the variable
+    pub hidden: Option<String>,
+    /// fixed value (the variable is no longer asked)
+    pub value: Option<VariableValueCfg>,
+}
+
+impl VariableOverrideCfg {
+    /// combine with an override from an upper layer (the fields defined by `upper` win)
+    pub(crate) fn overlay(&self, upper: &VariableOverrideCfg) -> VariableOverrideCfg {
+        VariableOverrideCfg {
+            name: self.name.clone(),
+            default_value: upper
+                .default_value
+                .clone()
+                .or_else(|| self.default_value.clone()),
+            ask: upper.ask.clone().or_else(|| self.ask.clone()),
+            hidden: upper.hidden.clone().or_else(|| self.hidden.clone()),
+            value: upper.value.clone().or_else(|| self.value.clone()),
+        }
+    }
+
+    /// combine with an override from another importing layer,
+    /// `None` if both define the same field with different values
+    pub(crate) fn merge(&self, other: &VariableOverrideCfg) -> Option<VariableOverrideCfg> {
+        fn merge_field<T: Clone + PartialEq>(a: &Option<T>, b: &Option<T>) -> Option<Option<T>> {
+            match (a, b) {
+                (Some(a), Some(b)) if a != b => None,
+                _ => Some(a.clone().or_else(|| b.clone())),
+            }
+        }
+        Some(VariableOverrideCfg {
+            name: self.name.clone(),
+            default_value: merge_field(&self.default_value, &other.default_value)?,
+            ask: merge_field(&self.ask, &other.ask)?,
+            hidden: merge_field(&self.hidden, &other.hidden)?,
+            value: merge_field(&self.value, &other.value)?,
+        })
+    }
+
+    pub(crate) fn apply(&self, variable: &mut VariableCfg) {
+        if let Some(default_value) = &self.default_value {
+            variable.default_value = Some(default_value.clone());
+            variable.default_from_cmd = None;
+        }
+        if let Some(ask) = &self.ask {
+            variable.ask = Some(ask.clone());
+        }
+        if let Some(hidden) = &self.hidden {
+            variable.hidden = Some(hidden.clone());
+        }
+        if let Some(value) = &self.value {
+            variable.default_value = Some(value.clone());
+            variable.default_from_cmd = None;
+            variable.env = None;
+            variable.hidden = Some("true".to_owned());
+            variable.fixed = true;
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use super::import_cfg::ImportCfg;
+use crate::Result;
+use crate::Variables;
+use crate::error::Error;
+use handlebars_misc_helpers::new_hbs;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+use tracing::debug;
+
+/// how the variables of a template layer are stored and seen, and where its files are applied,
+/// defined by the `namespace`, the `map`, the `rename` and the `dst_subfolder` of the imports
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub(crate) struct LayerScope {
+    /// path from the root where the answers are stored (eg `backend.db`), `None` for the root
+    namespace: Option<String>,
+    /// variable of the layer -> handlebars expression evaluated into the scope of the parent
+    map: BTreeMap<String, String>,
+    /// handlebars expression evaluated into the scope of the parent, relative to the folder of the parent
+    dst_subfolder: Option<String>,
+    /// path into the destination of the layer -> handlebars expression evaluated into the scope of the parent
+    rename: BTreeMap<String, String>,
+    parent: Option<Box<LayerScope>>,
+}
+
+impl LayerScope {
+    /// the scope of a layer imported via `import` from a layer with the current scope
+    pub(crate) fn child(&self, import: &ImportCfg) -> LayerScope {
+        if import.namespace.is_none()
+            && import.map.is_empty()
+            && import.dst_subfolder.is_none()
+            && import.rename.is_empty()
+        {
+            return self.clone();
+        }
+        let namespace = match (&self.namespace, &import.namespace) {
+            (Some(parent), Some(ns)) => Some(format!("{}.{}", parent, ns)),
+            (None, ns) | (ns, None) => ns.clone(),
+        };
+        LayerScope {
+            namespace,
+            map: import.map.clone(),
+            dst_subfolder: import.dst_subfolder.clone(),
+            rename: import.rename.clone(),
+            parent: Some(Box::new(self.clone())),
+        }
+    }
+
+    /// path from the root where the answers of the layer are stored, `None` for the root
+    pub(crate) fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// the key to store the value of the variable `name` of the layer
+    pub(crate) fn key(&self, name: &str) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{}.{}", namespace, name),
+            None => name.to_owned(),
+        }
+    }
+
+    /// store the value of the variable `name` of the layer (nested under the namespace, if any)
+    pub(crate) fn insert<V: serde::Serialize>(
+        &self,
+        variables: &mut Variables,
+        name: &str,
+        value: V,
+    ) -> Result<()> {
+        match &self.namespace {
+            Some(_) => variables.insert_nested(self.key(name), value),
+            None => variables.insert(name, value),
+        }
+    }
+
+    /// is the value of the variable computed from the parent (so it should not be asked)
+    pub(crate) fn is_mapped(&self, name: &str) -> bool {
+        self.map.contains_key(name)
+    }
+
+    /// the keys that could provide the value of `name` seen from the layer (from the closest)
+    pub(crate) fn candidate_keys(&self, name: &str) -> Vec<String> {
+        if self.is_mapped(name) {
+            return vec![];
+        }
+        let mut keys = vec![self.key(name)];
+        if let Some(parent) = &self.parent {
+            for key in parent.candidate_keys(name) {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+        keys
+    }
+
+    /// the variables as seen by the layer: the ones of the parent, overridden by the ones
+    /// of the namespace, and by the mapped ones
+    pub(crate) fn view(&self, variables: &Variables) -> Result<Variables> {
+        let Some(parent) = &self.parent else {
+            return Ok(variables.clone());
+        };
+        let parent_view = parent.view(variables)?;
+        let mut view = parent_view.clone();
+        if let Some(serde_yaml::Value::Mapping(values)) =
+            self.namespace.as_ref().and_then(|ns| variables.get(ns))
+        {
+            for (k, v) in values {
+                if let Some(k) = k.as_str() {
+                    view.insert(k, v)?;
+                }
+            }
+        }
+        let handlebars = new_hbs();
+        for (name, expr) in &self.map {
+            // keep the value (and its type) when the expression is only a reference to a variable
+            let reference = expr
+                .trim()
+                .strip_prefix("{{")
+                .and_then(|e| e.strip_suffix("}}"))
+                .map(str::trim)
+                .filter(|e| {
+                    e.chars()
+                        .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+                });
+            if let Some(value) = reference.and_then(|r| parent_view.get(r)) {
+                view.insert(name, value.clone())?;
+            } else {
+                match handlebars.render_template(expr, &parent_view) {
+                    Ok(value) => view.insert(name, value)?,
+                    Err(error) => debug!(name, expr, ?error, "mapped value not yet available"),
+                }
+            }
+        }
+        Ok(view)
+    }
+
+    /// the path (relative to the destination) of `relative` (relative to the destination of the layer),
+    /// after the `rename` and the `dst_subfolder` of the imports of the layer
+    pub(crate) fn dst_path(&self, relative: &Path, variables: &Variables) -> Result<PathBuf> {
+        let Some(parent) = &self.parent else {
+            return Ok(relative.to_path_buf());
+        };
+        let mut relative = relative.to_path_buf();
+        if !self.rename.is_empty() || self.dst_subfolder.is_some() {
+            let parent_view = parent.view(variables)?;
+            // the longest matching path (a file or one of its folders) is renamed
+            let renamed = self
+                .rename
+                .iter()
+                .filter_map(|(from, to)| {
+                    relative
+                        .strip_prefix(from)
+                        .ok()
+                        .map(|rest| (from, to, rest))
+                })
+                .max_by_key(|(from, _, _)| from.len());
+            if let Some((_, to, rest)) = renamed {
+                relative = join(&render_relative_path(to, &parent_view, "rename")?, rest);
+            }
+            if let Some(expr) = &self.dst_subfolder {
+                relative = join(
+                    &render_relative_path(expr, &parent_view, "dst_subfolder")?,
+                    &relative,
+                );
+            }
+        }
+        parent.dst_path(&relative, variables)
+    }
+}
+
+/// join without trailing separator when `relative` is empty
+fn join(base: &Path, relative: &Path) -> PathBuf {
+    if relative.as_os_str().is_empty() {
+        base.to_path_buf()
+    } else {
+        base.join(relative)
+    }
+}
+
+fn render_relative_path(expr: &str, variables: &Variables, field: &str) -> Result<PathBuf> {
+    let rendered = new_hbs()
+        .render_template(expr, variables)
+        .map_err(|source| Error::Handlebars {
+            when: format!("define {}", field),
+            template: Box::new(expr.to_owned()),
+            source: Box::new(source),
+        })?;
+    let path = PathBuf::from(rendered.trim());
+    if !path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(Error::InvalidDstPath { value: rendered });
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn import(namespace: Option<&str>, map: &[(&str, &str)]) -> ImportCfg {
+        ImportCfg {
+            namespace: namespace.map(|s| s.to_owned()),
+            map: map
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_view() {
+        let root = LayerScope::default();
+        let backend = root.child(&import(Some("backend"), &[]));
+        let db = backend.child(&import(
+            Some("db"),
+            &[("port", "{{ db_port }}"), ("label", "{{ name }}-db")],
+        ));
+        assert_eq!("name", root.key("name"));
+        assert_eq!("backend.name", backend.key("name"));
+        assert_eq!("backend.db.name", db.key("name"));
+        assert_eq!(
+            vec!["backend.db.name", "backend.name", "name"],
+            db.candidate_keys("name")
+        );
+        assert_eq!(Vec::<String>::new(), db.candidate_keys("port"));
+
+        let mut variables = Variables::default();
+        variables.insert("name", "root").unwrap();
+        variables.insert("version", "1.0").unwrap();
+        backend.insert(&mut variables, "name", "api").unwrap();
+        backend.insert(&mut variables, "db_port", 5432).unwrap();
+        db.insert(&mut variables, "name", "pg").unwrap();
+
+        let view = backend.view(&variables).unwrap();
+        assert_eq!(Some(&"api".into()), view.get("name"));
+        assert_eq!(Some(&"1.0".into()), view.get("version"));
+
+        let view = db.view(&variables).unwrap();
+        assert_eq!(Some(&"pg".into()), view.get("name"));
+        assert_eq!(Some(&"1.0".into()), view.get("version"));
+        assert_eq!(Some(&5432.into()), view.get("port"));
+        assert_eq!(Some(&"api-db".into()), view.get("label"));
+    }
+
+    #[test]
+    fn test_insert_into_namespace_with_same_name_as_variable() {
+        let root = LayerScope::default();
+        let backend = root.child(&import(Some("backend"), &[]));
+        let mut variables = Variables::default();
+        root.insert(&mut variables, "backend", "api").unwrap();
+
+        assert!(matches!(
+            backend.insert(&mut variables, "name", "foo"),
+            Err(Error::VariableNotANamespace { key, parent }) if key == "backend.name" && parent == "backend"
+        ));
+        // the answer of the variable is kept
+        assert_eq!(Some(&"api".into()), variables.get("backend"));
+    }
+
+    #[test]
+    fn test_dst_path() {
+        let root = LayerScope::default();
+        let crates = root.child(&ImportCfg {
+            dst_subfolder: Some("crates/{{ name }}".to_owned()),
+            ..Default::default()
+        });
+        let nested = crates.child(&ImportCfg {
+            namespace: Some("cli".to_owned()),
+            dst_subfolder: Some("{{ name }}".to_owned()),
+            ..Default::default()
+        });
+        let outside = root.child(&ImportCfg {
+            dst_subfolder: Some("../{{ name }}".to_owned()),
+            ..Default::default()
+        });
+        let renamed = crates.child(&ImportCfg {
+            rename: [
+                ("README.md", "docs/{{ name }}.md"),
+                ("ci", ".github"),
+                ("ci/main.yml", "ci.yml"),
+            ]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+            ..Default::default()
+        });
+
+        let mut variables = Variables::default();
+        variables.insert("name", "foo").unwrap();
+        variables.insert_nested("cli.name", "bar").unwrap();
+        let dst_path =
+            |scope: &LayerScope, relative: &str| scope.dst_path(Path::new(relative), &variables);
+        assert_eq!(PathBuf::from("a.txt"), dst_path(&root, "a.txt").unwrap());
+        assert_eq!(PathBuf::from("crates/foo"), dst_path(&crates, "").unwrap());
+        assert_eq!(
+            PathBuf::from("crates/foo/a.txt"),
+            dst_path(&crates, "a.txt").unwrap()
+        );
+        // rendered with the variables of the importing layer
+        assert_eq!(
+            PathBuf::from("crates/foo/foo/a.txt"),
+            dst_path(&nested, "a.txt").unwrap()
+        );
+        assert!(dst_path(&outside, "a.txt").is_err());
+        assert_eq!(
+            PathBuf::from("crates/foo/docs/foo.md"),
+            dst_path(&renamed, "README.md").unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("crates/foo/.github/build.yml"),
+            dst_path(&renamed, "ci/build.yml").unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("crates/foo/ci.yml"),
+            dst_path(&renamed, "ci/main.yml").unwrap()
+        );
+        assert_eq!(
+            PathBuf::from("crates/foo/cix.yml"),
+            dst_path(&renamed, "cix.yml").unwrap()
+        );
+    }
+}
@@ -1,5 +1,6 @@
 mod ignore_cfg;
 mod import_cfg;
+mod layer_scope;
 mod script_cfg;
 mod template_cfg;
 mod template_composite;
@@ -7,6 +8,7 @@ mod transform_values;
 mod variable_cfg;
 
 pub(crate) use import_cfg::*;
+pub(crate) use layer_scope::*;
 pub(crate) use template_cfg::*;
 pub(crate) use template_composite::*;
 pub(crate) use transform_values::*;
@@ -16,7 +18,6 @@ use crate::Result;
 use crate::path_pattern::PathPattern;
 use crate::scripts::Script;
 use crate::source_loc::SourceLoc;
-use crate::source_uri::SourceUri;
 use crate::timeline::FFIZER_DATASTORE_DIRNAME;
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -68,14 +69,8 @@ impl template_cfg::TemplateCfg {
         self.imports
             .iter()
             .map(|v| {
-                let uri = SourceUri::from_str(v.uri.as_str())?;
                 let subfolder = v.subfolder.as_ref().map(|x| PathBuf::from(x.as_str()));
-                let rev = v.rev.clone();
-                Ok(SourceLoc {
-                    uri,
-                    rev,
-                    subfolder,
-                })
+                SourceLoc::from_parts(v.uri.as_str(), v.rev.clone(), subfolder)
             })
             .collect()
     }
@@ -1,13 +1,14 @@
 use super::transform_values::TransformsValues;
 use crate::error::*;
 use schemars::JsonSchema;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
 use super::ignore_cfg::IgnoreCfg;
 use super::import_cfg::ImportCfg;
 use super::script_cfg::ScriptCfg;
-use super::variable_cfg::VariableCfg;
+use super::variable_cfg::{VariableCfg, VariableValueCfg};
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, JsonSchema)]
 #[serde(deny_unknown_fields, default)]
@@ -22,6 +23,8 @@ pub struct TemplateCfg {
     pub(crate) scripts: Vec<ScriptCfg>,
     /// set to true if the template content is under a `template` folder (not mixed with metadata)
     pub(crate) use_template_dir: bool,
+    /// named sets of variable's values (name of the variable -> value), selectable via `--preset`
+    pub(crate) presets: BTreeMap<String, BTreeMap<String, VariableValueCfg>>,
 }
 
 impl TemplateCfg {
@@ -61,13 +64,13 @@ impl TransformsValues for TemplateCfg {
             imports,
             scripts,
             use_template_dir: self.use_template_dir,
+            presets: self.presets.clone(),
         })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::super::variable_cfg::VariableValueCfg;
     use super::*;
     use pretty_assertions::assert_eq;
 
@@ -225,6 +228,27 @@ mod tests {
         assert_eq!(true, actual.use_template_dir);
     }
 
+    #[test]
+    fn test_deserialize_cfg_yaml_presets() {
+        let cfg_str = r#"
+        presets:
+            lib:
+                kind: lib
+                with_bin: false
+            cli:
+                kind: bin
+        "#;
+        let actual = serde_yaml::from_str::<TemplateCfg>(cfg_str).unwrap();
+        assert_eq!(
+            actual.presets.keys().collect::<Vec<_>>(),
+            vec!["cli", "lib"]
+        );
+        assert_eq!(
+            actual.presets["lib"]["with_bin"],
+            VariableValueCfg(serde_yaml::Value::Bool(false))
+        );
+    }
+
     #[test]
     fn test_accept_ignores_with_values() {
         let cfg_in_str = r#"
@@ -1,22 +1,42 @@
+use super::ignore_cfg::IgnoreCfg;
+use super::import_cfg::{ImportCfg, VariableOverrideCfg};
+use super::layer_scope::LayerScope;
 use super::template_cfg::TemplateCfg;
 use super::transform_values::TransformsValues;
-use super::variable_cfg::VariableCfg;
+use super::variable_cfg::{VariableCfg, find_referenced_keys};
+use crate::FetchOpts;
 use crate::Result;
 use crate::Variables;
+use crate::error::Error;
 use crate::files;
+use crate::git;
 use crate::graph::Graph;
 use crate::scripts::Script;
 use crate::source_file::SourceFile;
 use crate::source_loc::SourceLoc;
 use handlebars_misc_helpers::new_hbs;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::path::PathBuf;
 use tracing::{Level, debug, instrument, span, warn};
 #[derive(Debug, Clone)]
 pub struct TemplateLayer {
     order: usize,
     loc: SourceLoc,
     cfg: TemplateCfg,
+    /// overrides of variables defined by the importing layers
+    variable_overrides: BTreeMap<String, VariableOverrideCfg>,
+    scope: LayerScope,
+    download: Download,
+}
+
+/// where a template was downloaded
+#[derive(Debug, Clone, Default)]
+struct Download {
+    path: PathBuf,
+    /// the commit checked out (only for remote templates)
+    commit: Option<String>,
 }
 
 impl TransformsValues for TemplateLayer {
@@ -29,6 +49,9 @@ impl TransformsValues for TemplateLayer {
             order: self.order,
             loc: self.loc.clone(),
             cfg,
+            variable_overrides: self.variable_overrides.clone(),
+            scope: self.scope.clone(),
+            download: self.download.clone(),
         })
     }
 }
@@ -36,42 +59,110 @@ impl TransformsValues for TemplateLayer {
 #[derive(Debug, Clone)]
 pub struct TemplateComposite {
     layers: Vec<TemplateLayer>,
+    /// some imports have a condition (so the layers could change with the answers)
+    conditional: bool,
 }
 
 impl TemplateComposite {
     pub fn from_src(
         variables: &Variables,
-        offline: bool,
+        fetch: &FetchOpts,
         src: &SourceLoc,
     ) -> Result<TemplateComposite> {
         let mut templates = HashMap::new();
-        deep_download(variables, offline, src, &mut templates)?;
-        let layers = templates
-            .find_edges_ordered_by_depth(src)
+        let mut downloads = HashMap::new();
+        let conditional = deep_download(
+            variables,
+            fetch,
+            src,
+            &LayerScope::default(),
+            &mut templates,
+            &mut downloads,
+            &mut vec![],
+        )?;
+        let locs = templates.find_edges_ordered_by_depth(src)?;
+        let mut imports = find_layer_imports(&templates, &locs)?;
+        let layers = locs
             .into_iter()
             .enumerate()
             .map(|(i, k)| {
-                let v = templates.get(&k).expect("should exist").clone();
+                let mut v = templates.get(&k).expect("should exist").clone();
+                let import = imports.remove(&k).unwrap_or_default();
+                v.ignores.extend(import.ignores);
                 TemplateLayer {
                     order: i,
+                    variable_overrides: import.variable_overrides,
+                    scope: import.scope,
+                    download: downloads.remove(&k).unwrap_or_default(),
                     loc: k,
                     cfg: v,
                 }
             })
             .collect::<Vec<_>>();
         debug!(?layers);
-        Ok(TemplateComposite { layers })
+        Ok(TemplateComposite {
+            layers,
+            conditional,
+        })
+    }
+
+    pub fn has_conditional_imports(&self) -> bool {
+        self.conditional
+    }
+
+    /// are the same templates composed (eg after the evaluation of the conditions of the imports)
+    pub fn has_same_layers(&self, other: &TemplateComposite) -> bool {
+        self.layers
+            .iter()
+            .map(|layer| &layer.loc)
+            .eq(other.layers.iter().map(|layer| &layer.loc))
+    }
+
+    /// the commits checked out for the remote templates
+    pub fn find_commits(&self) -> Vec<(&SourceLoc, &str)> {
+        self.layers
+            .iter()
+            .filter_map(|layer| {
+                layer
+                    .download
+                    .commit
+                    .as_deref()
+                    .map(|commit| (&layer.loc, commit))
+            })
+            .collect()
     }
 
     pub fn find_variablecfgs(&self) -> Result<Vec<VariableCfg>> {
         let mut back = vec![];
-        let mut names = HashSet::new();
+        let mut keys = HashSet::new();
         for layer in &self.layers {
             let _span_ = span!(Level::DEBUG, "find_variablecfgs", layer = ?layer).entered();
-            for variable in layer.cfg.variables.clone() {
-                if !names.contains(&variable.name) {
-                    names.insert(variable.name.clone());
-                    back.push(variable.clone());
+            for mut variable in layer.cfg.variables.clone() {
+                if layer.scope.is_mapped(&variable.name) {
+                    continue;
+                }
+                variable.scope = layer.scope.clone();
+                if keys.insert(variable.key()) {
+                    if let Some(variable_override) = layer.variable_overrides.get(&variable.name) {
+                        variable_override.apply(&mut variable);
+                    }
+                    back.push(variable);
+                }
+            }
+        }
+        sort_by_dependencies(gather_by_group(back))
+    }
+
+    /// merge the presets of every layers (values from upper layers override the ones from imported layers)
+    pub fn find_presets(&self) -> Result<BTreeMap<String, Variables>> {
+        let mut back = BTreeMap::<String, Variables>::new();
+        for layer in &self.layers {
+            for (preset, values) in &layer.cfg.presets {
+                let variables = back.entry(preset.clone()).or_default();
+                for (name, value) in values {
+                    if !variables.contains_key(name) {
+                        variables.insert(name, &value.0)?;
+                    }
                 }
             }
         }
@@ -88,15 +179,53 @@ impl TemplateComposite {
             } else {
                 ""
             };
-            let path = layer.loc.as_local_path()?.join(template_dir);
+            let path = layer.download.path.join(template_dir);
             debug!(ignores = ?ignores);
             for childpath in files::find_childpaths(path, ignores) {
-                back.push(SourceFile::from((childpath, layer.order)));
+                let mut source_file = SourceFile::from((childpath, layer.order));
+                source_file.scope = layer.scope.clone();
+                back.push(source_file);
             }
         }
         Ok(back)
     }
 
+    /// the tree of imports from the root template (in depth first order),
+    /// a template imported several times is only expanded the first time
+    pub(crate) fn find_imports_tree(&self) -> Result<Vec<ImportNode>> {
+        let mut back = vec![];
+        let mut expanded = HashSet::new();
+        if let Some(root) = self.layers.first() {
+            self.push_import_node(root.loc.clone(), 0, &mut expanded, &mut back)?;
+        }
+        Ok(back)
+    }
+
+    fn push_import_node(
+        &self,
+        loc: SourceLoc,
+        depth: usize,
+        expanded: &mut HashSet<SourceLoc>,
+        back: &mut Vec<ImportNode>,
+    ) -> Result<()> {
+        let Some(layer) = self.layers.iter().find(|l| l.loc == loc) else {
+            return Ok(());
+        };
+        let repeated = !expanded.insert(loc.clone());
+        back.push(ImportNode {
+            depth,
+            order: layer.order,
+            loc,
+            repeated,
+        });
+        if !repeated {
+            for child in layer.cfg.find_sourcelocs()? {
+                self.push_import_node(child, depth + 1, expanded, back)?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn find_scripts(&self) -> Result<Vec<(&SourceLoc, Vec<Script>)>> {
         self.layers
             .iter()
@@ -105,28 +234,214 @@ impl TemplateComposite {
     }
 }
 
+/// a template into the tree of imports
+#[derive(Debug, Clone)]
+pub(crate) struct ImportNode {
+    pub depth: usize,
+    /// the order of the layer (to find variables, to apply files)
+    pub order: usize,
+    pub loc: SourceLoc,
+    /// already listed (with its imports) previously into the tree
+    pub repeated: bool,
+}
+
+/// what a layer inherits from the layers that import it
+#[derive(Debug, Clone, Default)]
+struct LayerImport {
+    variable_overrides: BTreeMap<String, VariableOverrideCfg>,
+    scope: LayerScope,
+    ignores: Vec<IgnoreCfg>,
+}
+
+/// for each layer: the overrides of variables defined by the import of the layer, combined with
+/// the ones of the importing layer, and the scope of its variables (`locs` should start by the root layer),
+/// the overrides of a layer imported by several layers are merged (they should not conflict)
+fn find_layer_imports(
+    templates: &HashMap<SourceLoc, TemplateCfg>,
+    locs: &[SourceLoc],
+) -> Result<HashMap<SourceLoc, LayerImport>> {
+    let mut back = HashMap::new();
+    if let Some(root) = locs.first() {
+        back.insert(root.clone(), LayerImport::default());
+    }
+    for loc in locs {
+        let Some(cfg) = templates.get(loc) else {
+            continue;
+        };
+        let inherited: LayerImport = back.get(loc).cloned().unwrap_or_default();
+        for (import, child) in cfg.imports.iter().zip(cfg.find_sourcelocs()?) {
+            let mut overrides = import
+                .variables
+                .iter()
+                .map(|v| (v.name.clone(), v.clone()))
+                .collect::<BTreeMap<_, _>>();
+            for (name, upper) in &inherited.variable_overrides {
+                let combined = match overrides.get(name) {
+                    Some(v) => v.overlay(upper),
+                    None => upper.clone(),
+                };
+                overrides.insert(name.clone(), combined);
+            }
+            match back.get_mut(&child) {
+                Some(LayerImport {
+                    variable_overrides, ..
+                }) => {
+                    for (name, other) in overrides {
+                        let merged = match variable_overrides.get(&name) {
+                            Some(v) => v.merge(&other).ok_or_else(|| {
+                                Error::ConflictingVariableOverrides {
+                                    name: name.clone(),
+                                    loc: child.to_string(),
+                                }
+                            })?,
+                            None => other,
+                        };
+                        variable_overrides.insert(name, merged);
+                    }
+                }
+                None => {
+                    back.insert(
+                        child,
+                        LayerImport {
+                            variable_overrides: overrides,
+                            scope: inherited.scope.child(import),
+                            ignores: [&inherited.ignores[..], &import.ignores[..]].concat(),
+                        },
+                    );
+                }
+            }
+        }
+    }
+    Ok(back)
+}
+
+/// move the variables of a group next to the first variable of the group,
+/// variables without group keep their position
+fn gather_by_group(variables: Vec<VariableCfg>) -> Vec<VariableCfg> {
+    let mut back = Vec::with_capacity(variables.len());
+    let mut groups = HashSet::new();
+    for variable in &variables {
+        match &variable.group {
+            None => back.push(variable.clone()),
+            Some(group) => {
+                if groups.insert(group.clone()) {
+                    back.extend(
+                        variables
+                            .iter()
+                            .filter(|v| v.group.as_ref() == Some(group))
+                            .cloned(),
+                    );
+                }
+            }
+        }
+    }
+    back
+}
+
+/// move the variables after the variables used into their definition (default_value, ask,...),
+/// variables keep their position when there is no dependency
+fn sort_by_dependencies(variables: Vec<VariableCfg>) -> Result<Vec<VariableCfg>> {
+    let (keys, mut dependencies): (Vec<_>, Vec<_>) =
+        find_referenced_keys(&variables)?.into_iter().unzip();
+    let keys = keys
+        .into_iter()
+        .enumerate()
+        .map(|(i, k)| (k, i))
+        .collect::<HashMap<_, _>>();
+    let mut remaining = variables.into_iter().map(Some).collect::<Vec<_>>();
+    let mut back = Vec::with_capacity(remaining.len());
+    while back.len() < remaining.len() {
+        let next = (0..remaining.len())
+            .find(|i| remaining[*i].is_some() && dependencies[*i].is_empty())
+            .and_then(|i| remaining[i].take());
+        match next {
+            Some(variable) => {
+                let key = variable.key();
+                dependencies.iter_mut().for_each(|d| {
+                    d.remove(&key);
+                });
+                back.push(variable);
+            }
+            None => {
+                // every remaining variables depends on a cycle, follow dependencies to find it
+                let mut path: Vec<String> = vec![];
+                let mut i = (0..remaining.len())
+                    .find(|i| remaining[*i].is_some())
+                    .expect("a remaining variable");
+                while let Some(name) = dependencies[i].iter().next() {
+                    if let Some(start) = path.iter().position(|n| n == name) {
+                        let mut cycle = path.split_off(start);
+                        cycle.push(name.clone());
+                        return Err(Error::VariablesDependencyCycle { names: cycle });
+                    }
+                    path.push(name.clone());
+                    i = keys[name];
+                }
+                unreachable!("a variable without dependency should have been selected");
+            }
+        }
+    }
+    Ok(back)
+}
+
 impl Graph for HashMap<SourceLoc, TemplateCfg> {
     type K = SourceLoc;
     type V = TemplateCfg;
     fn find_node(&self, k: &Self::K) -> Option<&Self::V> {
         self.get(k)
     }
-    fn find_edges_direct(&self, v: &Self::V) -> Vec<Self::K> {
+    fn find_edges_direct(&self, v: &Self::V) -> Result<Vec<Self::K>> {
         v.find_sourcelocs()
-            .expect("TODO find_sourcelocs without error")
     }
 }
 
 //struct Template;
-#[instrument(skip(variables, templates))]
+/// `chain` is the list of imports from the root to `src` (with the local path of the templates),
+/// to detect cycles (the same template could be reached via different uri, eg `{{ ffizer_src_uri }}/../a`)
+#[instrument(skip(variables, templates, chain))]
 fn deep_download(
     variables: &Variables,
-    offline: bool,
+    fetch: &FetchOpts,
     src: &SourceLoc,
+    scope: &LayerScope,
     templates: &mut HashMap<SourceLoc, TemplateCfg>,
-) -> Result<()> {
+    downloads: &mut HashMap<SourceLoc, Download>,
+    chain: &mut Vec<(SourceLoc, PathBuf)>,
+) -> Result<bool> {
+    let mut conditional = false;
+    if chain.iter().any(|(loc, _)| loc == src) {
+        return Err(imports_cycle_error(chain, src, |(loc, _)| loc == src));
+    }
     if !templates.contains_key(src) {
-        let template_base_path = &src.download(offline)?;
+        let template_base_path = &src.download(fetch)?;
+        let canonical_path =
+            template_base_path
+                .canonicalize()
+                .map_err(|source| Error::CanonicalizePath {
+                    path: template_base_path.clone(),
+                    source,
+                })?;
+        if chain.iter().any(|(_, path)| path == &canonical_path) {
+            return Err(imports_cycle_error(chain, src, |(_, path)| {
+                path == &canonical_path
+            }));
+        }
+        let commit = match src.uri.host {
+            Some(_) => Some(git::find_head_commit(template_base_path).map_err(|source| {
+                Error::GitFindCommit {
+                    path: template_base_path.clone(),
+                    source,
+                }
+            })?),
+            None => None,
+        };
+        downloads.insert(
+            src.clone(),
+            Download {
+                path: template_base_path.clone(),
+                commit,
+            },
+        );
         // update cfg with variables defined by user
         let template_cfg = TemplateCfg::from_template_folder(template_base_path)?;
         // update cfg with variables defined by cli (use to update default_value)
@@ -134,16 +449,70 @@ fn deep_download(
         variables_children.insert("ffizer_src_uri", src.uri.raw.clone())?;
         variables_children.insert("ffizer_src_rev", src.rev.clone())?;
         //variables_children.insert("ffizer_src_subfolder".to_owned(), src.subfolder.clone());
-        let template_cfg_for_imports =
+        let mut template_cfg_for_imports =
             render_imports_only(&template_cfg, &variables_children, false)?;
+        let view = scope.view(&variables_children)?;
+        conditional = template_cfg.imports.iter().any(|i| i.condition.is_some());
+        let mut enabled = vec![];
+        for import in &template_cfg_for_imports.imports {
+            enabled.push(is_import_enabled(import, &view));
+        }
+        let mut enabled = enabled.into_iter();
+        template_cfg_for_imports
+            .imports
+            .retain(|_| enabled.next().unwrap_or(false));
         let children = template_cfg_for_imports.find_sourcelocs()?;
+        let scopes = template_cfg_for_imports
+            .imports
+            .iter()
+            .map(|import| scope.child(import))
+            .collect::<Vec<_>>();
         //WARN: Do insert a rendered templates because the value of are not yet defined
         templates.insert(src.clone(), template_cfg_for_imports);
-        for child in children {
-            deep_download(&variables_children, offline, &child, templates)?;
+        chain.push((src.clone(), canonical_path));
+        for (child, child_scope) in children.iter().zip(scopes) {
+            conditional |= deep_download(
+                &variables_children,
+                fetch,
+                child,
+                &child_scope,
+                templates,
+                downloads,
+                chain,
+            )?;
+        }
+        chain.pop();
+    }
+    Ok(conditional)
+}
+
+fn imports_cycle_error<F>(chain: &[(SourceLoc, PathBuf)], src: &SourceLoc, is_src: F) -> Error
+where
+    F: Fn(&(SourceLoc, PathBuf)) -> bool,
+{
+    let start = chain.iter().position(is_src).unwrap_or_default();
+    let mut locs = chain[start..]
+        .iter()
+        .map(|(loc, _)| loc.to_string())
+        .collect::<Vec<_>>();
+    locs.push(src.to_string());
+    Error::ImportsCycle { locs }
+}
+
+/// evaluate the condition of the import, a condition that can not be rendered yet
+/// (eg it uses a variable not yet defined) disables the import until the next composition
+/// the import is enabled if its condition is rendered as anything other than `false` or empty
+fn is_import_enabled(import: &ImportCfg, variables: &Variables) -> bool {
+    let Some(condition) = &import.condition else {
+        return true;
+    };
+    match new_hbs().render_template(condition, variables) {
+        Ok(rendered) => !matches!(rendered.trim(), "" | "false"),
+        Err(err) => {
+            debug!(uri = import.uri, condition, ?err, "defer the import");
+            false
         }
     }
-    Ok(())
 }
 
 impl TransformsValues for TemplateComposite {
@@ -153,7 +522,10 @@ impl TransformsValues for TemplateComposite {
         F: Fn(&str) -> String,
     {
         let layers = self.layers.transforms_values(render)?;
-        Ok(TemplateComposite { layers })
+        Ok(TemplateComposite {
+            layers,
+            conditional: self.conditional,
+        })
     }
 }
 
@@ -163,19 +535,31 @@ pub(crate) fn render_composite(
     log_warning: bool,
 ) -> Result<TemplateComposite> {
     let handlebars = new_hbs();
-    let render = |v: &str| {
-        let r = handlebars.render_template(v, variables);
-        match r {
-            Ok(s) => s,
-            Err(e) => {
-                if log_warning {
-                    warn!(input = ?v, error = ?e, "failed to convert")
+    let layers = template_composite
+        .layers
+        .iter()
+        .map(|layer| {
+            // each layer is rendered with the variables of its scope
+            let variables = layer.scope.view(variables)?;
+            let render = |v: &str| {
+                let r = handlebars.render_template(v, &variables);
+                match r {
+                    Ok(s) => s,
+                    Err(e) => {
+                        if log_warning {
+                            warn!(input = ?v, error = ?e, "failed to convert")
+                        }
+                        v.into()
+                    }
                 }
-                v.into()
-            }
-        }
-    };
-    template_composite.transforms_values(&render)
+            };
+            layer.transforms_values(&render)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(TemplateComposite {
+        layers,
+        conditional: template_composite.conditional,
+    })
 }
 
 fn render_imports_only(
@@ -206,5 +590,173 @@ fn render_imports_only(
         imports,
         scripts,
         use_template_dir: template_cfg.use_template_dir,
+        presets: template_cfg.presets.clone(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::VariableValueCfg;
+    use pretty_assertions::assert_eq;
+
+    fn new_variablecfg(name: &str, group: Option<&str>) -> VariableCfg {
+        VariableCfg {
+            name: name.to_owned(),
+            group: group.map(|s| s.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_gather_by_group() {
+        let input = vec![
+            new_variablecfg("a", None),
+            new_variablecfg("b", Some("g1")),
+            new_variablecfg("c", None),
+            new_variablecfg("d", Some("g2")),
+            new_variablecfg("e", Some("g1")),
+        ];
+        let actual = gather_by_group(input)
+            .into_iter()
+            .map(|v| v.name)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["a", "b", "e", "c", "d"], actual);
+    }
+
+    fn new_variablecfg_with_default(name: &str, default_value: &str) -> VariableCfg {
+        VariableCfg {
+            name: name.to_owned(),
+            default_value: Some(VariableValueCfg(default_value.into())),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sort_by_dependencies() {
+        let input = vec![
+            new_variablecfg("a", None),
+            new_variablecfg_with_default("b", "{{ d }}-{{ ffizer_dst_folder }}"),
+            new_variablecfg("c", None),
+            new_variablecfg_with_default("d", "{{ e__label }}"),
+            new_variablecfg("e", None),
+        ];
+        let actual = sort_by_dependencies(input)
+            .unwrap()
+            .into_iter()
+            .map(|v| v.name)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["a", "c", "e", "d", "b"], actual);
+    }
+
+    #[test]
+    fn test_sort_by_dependencies_with_cycle() {
+        let input = vec![
+            new_variablecfg("a", None),
+            new_variablecfg_with_default("b", "{{ d }}"),
+            new_variablecfg_with_default("c", "{{ b }}"),
+            new_variablecfg_with_default("d", "{{ c }}"),
+        ];
+        let actual = sort_by_dependencies(input);
+        match actual {
+            Err(Error::VariablesDependencyCycle { names }) => {
+                assert_eq!(vec!["d", "c", "b", "d"], names)
+            }
+            _ => panic!("unexpected result: {:?}", actual),
+        }
+    }
+
+    fn new_override(name: &str, default_value: &str) -> VariableOverrideCfg {
+        VariableOverrideCfg {
+            name: name.to_owned(),
+            default_value: Some(VariableValueCfg(default_value.into())),
+            ..Default::default()
+        }
+    }
+
+    /// `root` imports `a` and `b`, that both import `c` (with the overrides)
+    fn find_imports_of_shared_layer(
+        from_a: Vec<VariableOverrideCfg>,
+        from_b: Vec<VariableOverrideCfg>,
+    ) -> Result<BTreeMap<String, VariableOverrideCfg>> {
+        let loc = |uri: &str| SourceLoc::from_parts(uri, None, None).unwrap();
+        let import = |uri: &str, variables: Vec<VariableOverrideCfg>| ImportCfg {
+            uri: uri.to_owned(),
+            variables,
+            ..Default::default()
+        };
+        let cfg = |imports: Vec<ImportCfg>| TemplateCfg {
+            imports,
+            ..Default::default()
+        };
+        let templates = HashMap::from([
+            (
+                loc("/tmpl/root"),
+                cfg(vec![import("/tmpl/a", vec![]), import("/tmpl/b", vec![])]),
+            ),
+            (loc("/tmpl/a"), cfg(vec![import("/tmpl/c", from_a)])),
+            (loc("/tmpl/b"), cfg(vec![import("/tmpl/c", from_b)])),
+            (loc("/tmpl/c"), cfg(vec![])),
+        ]);
+        let locs = ["/tmpl/root", "/tmpl/a", "/tmpl/b", "/tmpl/c"].map(loc);
+        let mut imports = find_layer_imports(&templates, &locs)?;
+        Ok(imports.remove(&loc("/tmpl/c")).unwrap().variable_overrides)
+    }
+
+    #[test]
+    fn test_find_layer_imports_should_merge_overrides_of_importers() {
+        let actual = find_imports_of_shared_layer(
+            vec![new_override("x", "1"), new_override("z", "3")],
+            vec![new_override("y", "2"), new_override("z", "3")],
+        )
+        .unwrap();
+        assert_eq!(
+            actual,
+            BTreeMap::from([
+                ("x".to_owned(), new_override("x", "1")),
+                ("y".to_owned(), new_override("y", "2")),
+                ("z".to_owned(), new_override("z", "3")),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_find_layer_imports_should_fail_on_conflicting_overrides() {
+        let actual = find_imports_of_shared_layer(
+            vec![new_override("x", "1")],
+            vec![new_override("x", "2")],
+        );
+        assert!(matches!(
+            actual,
+            Err(Error::ConflictingVariableOverrides { name, .. }) if name == "x"
+        ));
+    }
+
+    #[test]
+    fn test_is_import_enabled() {
+        let mut variables = Variables::default();
+        variables.insert("yes", true).unwrap();
+        variables.insert("no", false).unwrap();
+        variables.insert("kind", "docker").unwrap();
+        let import = |condition: Option<&str>| ImportCfg {
+            condition: condition.map(|s| s.to_owned()),
+            ..Default::default()
+        };
+        assert!(is_import_enabled(&import(None), &variables));
+        assert!(is_import_enabled(&import(Some("{{ yes }}")), &variables));
+        assert!(!is_import_enabled(&import(Some("{{ no }}")), &variables));
+        assert!(!is_import_enabled(
+            &import(Some("{{#if no}}true{{/if}}")),
+            &variables
+        ));
+        // not yet defined
+        assert!(!is_import_enabled(
+            &import(Some("{{ undefined }}")),
+            &variables
+        ));
+        // not a bool
+        assert!(is_import_enabled(&import(Some("{{ kind }}")), &variables));
+        assert!(is_import_enabled(&import(Some("1")), &variables));
+        assert!(is_import_enabled(&import(Some("yes")), &variables));
+    }
+}
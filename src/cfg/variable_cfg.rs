@@ -1,8 +1,13 @@
 use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
 
+use super::layer_scope::LayerScope;
 use super::transform_values::TransformsValues;
 use crate::Result;
 use crate::variable_def::LabelValue;
+use handlebars::Path;
+use handlebars::template::{HelperTemplate, Parameter, Template, TemplateElement};
 use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
 
 #[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, Eq, JsonSchema)]
@@ -11,12 +16,186 @@ pub struct VariableCfg {
     pub name: String,
     /// optionnal default value
     pub default_value: Option<VariableValueCfg>,
+    /// command to run to compute the default value (from its standard output), it's run only after confirmation (like scripts)
+    pub default_from_cmd: Option<String>,
     /// sentence to ask the value (default to the name on variable)
     pub ask: Option<String>,
+    /// help text displayed under the prompt
+    #[serde(alias = "description")]
+    pub help: Option<String>,
+    /// name of the group of related variables (asked together under a header)
+    pub group: Option<String>,
     /// is the variable hidden to the user (could be usefull to cache shared variable/data)
     pub hidden: Option<String>,
     /// if non-empty then the value should selected into the list of value
     pub select_in_values: Option<VariableValuesCfg>,
+    /// is the value a secret (prompted as password, masked and never saved into `.ffizer/options.yaml`)
+    pub secret: Option<bool>,
+    /// is a value required, when not provided in no-interaction mode the generation fails (default to true if no `default_value`)
+    pub required: Option<bool>,
+    /// name of an environment variable (eg `USER`) whose value is used as default value (when defined)
+    pub env: Option<String>,
+    /// scope of the layer that defines the variable
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub(crate) scope: LayerScope,
+    /// the value is fixed by an importing template (saved, environment and user values are ignored)
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub(crate) fixed: bool,
+}
+
+impl VariableCfg {
+    pub(crate) fn is_secret(&self) -> bool {
+        self.secret.unwrap_or(false)
+    }
+
+    /// the key of the value into the variables (eg `backend.name` if defined into the namespace `backend`)
+    pub(crate) fn key(&self) -> String {
+        self.scope.key(&self.name)
+    }
+
+    /// names used into the handlebars expressions of the templated fields
+    /// (suffixes `__label` and `__idx` are removed)
+    pub(crate) fn referenced_names(&self) -> Result<BTreeSet<String>> {
+        let templates = RefCell::new(vec![]);
+        self.transforms_values(&|s: &str| {
+            templates.borrow_mut().push(s.to_owned());
+            s.to_owned()
+        })?;
+        let mut names = BTreeSet::new();
+        for template in templates.into_inner() {
+            // an invalid template is reported when it's rendered
+            if let Ok(template) = Template::compile(&template) {
+                collect_root_names(&template, &mut names);
+            }
+        }
+        Ok(names
+            .into_iter()
+            .map(|name| {
+                name.strip_suffix("__label")
+                    .or_else(|| name.strip_suffix("__idx"))
+                    .map(str::to_owned)
+                    .unwrap_or(name)
+            })
+            .filter(|name| name != &self.name)
+            .collect())
+    }
+}
+
+/// collect the first names of the paths used into the template (eg `project` for `project.name`),
+/// the helper names and the literals are not paths
+fn collect_root_names(template: &Template, names: &mut BTreeSet<String>) {
+    for element in &template.elements {
+        collect_root_names_of_element(element, names);
+    }
+}
+
+fn collect_root_names_of_element(element: &TemplateElement, names: &mut BTreeSet<String>) {
+    match element {
+        TemplateElement::Expression(helper) | TemplateElement::HtmlExpression(helper) => {
+            // without params, `{{ name }}` is a variable (not a helper)
+            if helper.params.is_empty()
+                && helper.hash.is_empty()
+                && let Parameter::Name(name) = &helper.name
+            {
+                names.insert(name.clone());
+            }
+            collect_root_names_of_helper(helper, names);
+        }
+        TemplateElement::HelperBlock(helper) => {
+            collect_root_names_of_helper(helper, names);
+            // into `each` and `with`, the paths are relative to the item (not to the root)
+            let is_new_context =
+                matches!(&helper.name, Parameter::Name(n) if n == "each" || n == "with");
+            if !is_new_context && let Some(template) = &helper.template {
+                collect_root_names(template, names);
+            }
+            if let Some(template) = &helper.inverse {
+                collect_root_names(template, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn collect_root_names_of_helper(helper: &HelperTemplate, names: &mut BTreeSet<String>) {
+    for param in std::iter::once(&helper.name)
+        .chain(helper.params.iter())
+        .chain(helper.hash.values())
+    {
+        match param {
+            Parameter::Path(Path::Relative((_, raw))) => {
+                let raw = raw.strip_prefix("@root.").unwrap_or(raw);
+                let raw = raw.strip_prefix("this.").unwrap_or(raw);
+                let name = raw.split(['.', '/', '[']).next().unwrap_or_default();
+                if !name.is_empty() && name != "this" && name != ".." {
+                    names.insert(name.to_owned());
+                }
+            }
+            Parameter::Subexpression(subexpression) => {
+                collect_root_names_of_element(subexpression.as_element(), names);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// for each variable (by key), the keys of the variables (of the list) used into its definition
+pub(crate) fn find_referenced_keys(
+    list: &[VariableCfg],
+) -> Result<Vec<(String, BTreeSet<String>)>> {
+    let keys = list.iter().map(|v| v.key()).collect::<BTreeSet<_>>();
+    list.iter()
+        .map(|v| {
+            let key = v.key();
+            let references = v
+                .referenced_names()?
+                .iter()
+                .filter_map(|name| {
+                    v.scope
+                        .candidate_keys(name)
+                        .into_iter()
+                        .find(|k| k != &key && keys.contains(k))
+                })
+                .collect();
+            Ok((key, references))
+        })
+        .collect()
+}
+
+/// find the variables (by key) whose definition depends (directly or not) on the variable `key`
+pub(crate) fn find_dependents(list: &[VariableCfg], key: &str) -> Result<BTreeSet<String>> {
+    let references = find_referenced_keys(list)?;
+    let mut dependents = BTreeSet::new();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (dependent, keys) in &references {
+            if !dependents.contains(dependent)
+                && keys.iter().any(|k| k == key || dependents.contains(k))
+            {
+                dependents.insert(dependent.to_string());
+                changed = true;
+            }
+        }
+    }
+    dependents.remove(key);
+    Ok(dependents)
+}
+
+/// find the namespaces (and their parents) where the variables are stored (eg `backend` and `backend.db`)
+pub(crate) fn find_namespaces(list: &[VariableCfg]) -> BTreeSet<String> {
+    list.iter()
+        .filter_map(|cfg| cfg.scope.namespace())
+        .flat_map(|namespace| {
+            namespace
+                .match_indices('.')
+                .map(|(i, _)| namespace[..i].to_owned())
+                .chain(std::iter::once(namespace.to_owned()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
 }
 
 impl TransformsValues for VariableCfg {
@@ -27,15 +206,25 @@ impl TransformsValues for VariableCfg {
     {
         let name = self.name.transforms_values(render)?;
         let default_value = self.default_value.transforms_values(render)?;
+        let default_from_cmd = self.default_from_cmd.transforms_values(render)?;
         let ask = self.ask.transforms_values(render)?;
+        let help = self.help.transforms_values(render)?;
         let hidden = self.hidden.transforms_values(render)?;
         let select_in_values = self.select_in_values.transforms_values(render)?;
         Ok(VariableCfg {
             name,
             default_value,
+            default_from_cmd,
             ask,
+            help,
+            group: self.group.clone(),
             hidden,
             select_in_values,
+            secret: self.secret,
+            required: self.required,
+            env: self.env.clone(),
+            scope: self.scope.clone(),
+            fixed: self.fixed,
         })
     }
 }
@@ -142,3 +331,86 @@ impl TransformsValues for VariableValuesCfg {
         Ok(v)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::ImportCfg;
+    use pretty_assertions::assert_eq;
+
+    fn variable_cfg(name: &str, default_value: &str) -> VariableCfg {
+        VariableCfg {
+            name: name.to_owned(),
+            default_value: Some(VariableValueCfg(default_value.into())),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_find_dependents() {
+        let list = vec![
+            variable_cfg("a", "x"),
+            variable_cfg("b", "{{ a }}-b"),
+            variable_cfg("c", "{{#if (eq b__label 'y')}}c{{/if}}"),
+            variable_cfg("d", "{{ to_snake_case ab }}"),
+            VariableCfg {
+                name: "e".to_owned(),
+                hidden: Some("{{ c }}".to_owned()),
+                ..Default::default()
+            },
+        ];
+        let actual = find_dependents(&list, "a").unwrap();
+        let expected = ["b", "c", "e"]
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(actual, expected);
+        assert_eq!(find_dependents(&list, "e").unwrap(), BTreeSet::new());
+    }
+
+    #[test]
+    fn test_find_namespaces() {
+        let import = |namespace: &str| ImportCfg {
+            namespace: Some(namespace.to_owned()),
+            ..Default::default()
+        };
+        let scope = LayerScope::default().child(&import("backend"));
+        let list = vec![
+            variable_cfg("a", "x"),
+            VariableCfg {
+                name: "b".to_owned(),
+                scope: scope.clone(),
+                ..Default::default()
+            },
+            VariableCfg {
+                name: "c".to_owned(),
+                scope: scope.child(&import("db")),
+                ..Default::default()
+            },
+        ];
+        let expected = ["backend", "backend.db"]
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(find_namespaces(&list), expected);
+    }
+
+    #[rstest::rstest]
+    #[case::quoted_literal(r#"{{#if (eq kind "lib")}}lib{{/if}}"#, &["kind"])]
+    #[case::single_quoted_literal("{{#if (eq kind 'lib')}}lib{{/if}}", &["kind"])]
+    #[case::helper_position("{{ to_snake_case project_name }}", &["project_name"])]
+    #[case::helper_in_subexpression("{{ trim (to_snake_case project_name) }}", &["project_name"])]
+    #[case::non_root_segments("{{ project.name }}-{{ db.kind__label }}", &["db", "project"])]
+    #[case::suffixes("{{ kind__label }}-{{ kind__idx }}", &["kind"])]
+    #[case::block_bodies("{{#if a}}{{ b }}{{else}}{{ c }}{{/if}}", &["a", "b", "c"])]
+    #[case::each_body("{{#each items}}{{ name }}{{/each}}", &["items"])]
+    #[case::self_reference("{{ x }}", &[])]
+    fn test_referenced_names(#[case] default_value: &str, #[case] expected: &[&str]) {
+        let actual = variable_cfg("x", default_value).referenced_names().unwrap();
+        let expected = expected
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(actual, expected);
+    }
+}
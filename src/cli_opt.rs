@@ -1,6 +1,9 @@
 use crate::source_loc::SourceLoc;
+use crate::source_uri::SourceUri;
 use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser, Debug, Clone)]
 #[command(
@@ -35,6 +38,12 @@ pub enum Command {
 
     /// test a template against its samples
     TestSamples(TestSamplesOpts),
+
+    /// Show the tree of the templates imported by a template (with the order of the layers)
+    ShowImports(ShowImportsOpts),
+
+    /// Manage the cache of the remote templates and of the archives
+    Cache(CacheOpts),
 }
 
 #[derive(Args, Debug, Default, Clone)]
@@ -51,9 +60,8 @@ pub struct ApplyOpts {
     #[arg(short = 'y', long = "no-interaction")]
     pub no_interaction: bool,
 
-    /// in offline, only local templates or cached templates are used
-    #[arg(long = "offline")]
-    pub offline: bool,
+    #[command(flatten)]
+    pub fetch: FetchOpts,
 
     #[command(flatten)]
     pub src: SourceLoc,
@@ -70,6 +78,14 @@ pub struct ApplyOpts {
     /// set variable's value from cli ("key=value")
     #[arg(short = 'v', long = "variables", value_parser = parse_keyvalue)]
     pub key_value: Vec<(String, String)>,
+
+    /// name of a preset (set of variable's values defined by the template) to use
+    #[arg(long = "preset", value_name = "NAME")]
+    pub preset: Option<String>,
+
+    /// list the variables of the template (without applying it)
+    #[arg(long = "list-variables")]
+    pub list_variables: bool,
 }
 
 #[derive(Args, Debug, Default, Clone)]
@@ -86,9 +102,16 @@ pub struct ReapplyOpts {
     #[arg(short = 'y', long = "no-interaction")]
     pub no_interaction: bool,
 
-    /// in offline, only local templates or cached templates are used
-    #[arg(long = "offline")]
-    pub offline: bool,
+    #[command(flatten)]
+    pub fetch: FetchOpts,
+
+    /// checkout the commits of the remote templates saved into `.ffizer/lock.yaml` (instead of their rev)
+    #[arg(long = "locked", conflicts_with = "update")]
+    pub locked: bool,
+
+    /// fetch the last commits of the revs of the remote templates (and show the updated templates)
+    #[arg(long = "update", conflicts_with = "offline")]
+    pub update: bool,
 
     /// destination folder (created if doesn't exist)
     #[arg(
@@ -104,6 +127,45 @@ pub struct ReapplyOpts {
     pub key_value: Vec<(String, String)>,
 }
 
+/// how the remote templates are retrieved
+#[derive(Args, Debug, Clone)]
+pub struct FetchOpts {
+    /// in offline, only local templates or cached templates are used
+    #[arg(long = "offline")]
+    pub offline: bool,
+
+    /// fetch the remote templates, even if they were fetched less than `cache-ttl` ago
+    #[arg(long = "refresh", conflicts_with = "offline")]
+    pub refresh: bool,
+
+    /// duration (in seconds) while a cached remote template is used without fetching it again
+    #[arg(long = "cache-ttl", value_name = "SECONDS", default_value_t = DEFAULT_CACHE_TTL_SECS)]
+    pub cache_ttl: u64,
+
+    /// fail when a remote template can not be fetched (instead of using its previous cached checkout)
+    #[arg(long = "strict-fetch", conflicts_with = "offline")]
+    pub strict_fetch: bool,
+
+    /// commits to checkout in place of the revs of the remote templates, by (uri, rev)
+    /// (every remote template should be locked)
+    #[arg(skip)]
+    pub locked_commits: Option<BTreeMap<(String, Option<String>), String>>,
+}
+
+const DEFAULT_CACHE_TTL_SECS: u64 = 5 * 60;
+
+impl Default for FetchOpts {
+    fn default() -> Self {
+        FetchOpts {
+            offline: false,
+            refresh: false,
+            cache_ttl: DEFAULT_CACHE_TTL_SECS,
+            strict_fetch: false,
+            locked_commits: None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, ValueEnum, Default)]
 pub enum AskConfirmation {
     #[default]
@@ -156,22 +218,112 @@ fn parse_keyvalue(src: &str) -> Result<(String, String), String> {
 pub struct TestSamplesOpts {
     #[command(flatten)]
     pub src: SourceLoc,
-    /// in offline, only local templates or cached templates are used
-    #[arg(long = "offline")]
-    pub offline: bool,
+    #[command(flatten)]
+    pub fetch: FetchOpts,
 
     /// interactive review mode, allow to update sample from generated
     #[arg(long = "review")]
     pub review: bool,
 }
 
+#[derive(Parser, Debug, Default, Clone)]
+pub struct ShowImportsOpts {
+    #[command(flatten)]
+    pub src: SourceLoc,
+
+    #[command(flatten)]
+    pub fetch: FetchOpts,
+
+    /// set variable's value from cli ("key=value"), used by the conditions of imports
+    #[arg(short = 'v', long = "variables", value_parser = parse_keyvalue)]
+    pub key_value: Vec<(String, String)>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CacheOpts {
+    #[command(subcommand)]
+    pub cmd: CacheCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CacheCommand {
+    /// List the cached remote templates and extracted archives (with size, last fetch and commit)
+    List,
+
+    /// Remove the cached templates not fetched (or extracted) for a while, and the corrupted ones (eg interrupted clone)
+    Prune(CachePruneOpts),
+
+    /// Remove every cached templates (or only the ones of an uri)
+    Clear(CacheClearOpts),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CachePruneOpts {
+    /// remove the templates fetched for the last time before this duration (eg "30d", "12h", "90m", "3600s")
+    #[arg(
+        long = "older-than",
+        value_name = "DURATION",
+        default_value = "30d",
+        value_parser = parse_duration
+    )]
+    pub older_than: Duration,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CacheClearOpts {
+    /// uri of the remote template (with all its revs) or path of the archive to remove
+    #[arg(value_name = "URI")]
+    pub uri: Option<SourceUri>,
+}
+
+fn parse_duration(src: &str) -> Result<Duration, String> {
+    let (value, unit) = src.split_at(src.find(|c: char| !c.is_ascii_digit()).unwrap_or(src.len()));
+    let factor = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => 0,
+    };
+    match value
+        .parse::<u64>()
+        .ok()
+        .and_then(|v| v.checked_mul(factor))
+    {
+        Some(secs) if factor > 0 => Ok(Duration::from_secs(secs)),
+        _ => Err(format!(
+            "invalid duration '{}' (expected a number with an unit: s, m, h or d, eg '30d')",
+            src
+        )),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rstest::rstest;
 
     #[test]
     fn verify_cli() {
         use clap::CommandFactory;
         CliOpts::command().debug_assert()
     }
+
+    #[rstest]
+    #[case("3600", Some(3600))]
+    #[case("90s", Some(90))]
+    #[case("90m", Some(90 * 60))]
+    #[case("12h", Some(12 * 3600))]
+    #[case("30d", Some(30 * 24 * 3600))]
+    #[case("30w", None)]
+    #[case("d", None)]
+    #[case("", None)]
+    #[case("99999999999999999d", None)]
+    #[case("99999999999999999999", None)]
+    fn test_parse_duration(#[case] input: &str, #[case] expected: Option<u64>) {
+        assert_eq!(
+            parse_duration(input).ok(),
+            expected.map(Duration::from_secs)
+        );
+    }
 }
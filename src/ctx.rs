@@ -1,12 +1,16 @@
 use super::Ctx;
+use crate::cfg::VariableCfg;
 use crate::error::*;
 use crate::timeline::get_saved_variables;
 use crate::variables::Variables;
+use std::collections::{BTreeMap, BTreeSet};
 
 pub(crate) struct VariablesFromCtx {
     pub src: Variables,
     pub cli: Variables,
     pub saved: Variables,
+    /// default values from the user configuration
+    pub user: Variables,
 }
 
 pub(crate) fn extract_variables(ctx: &Ctx) -> Result<VariablesFromCtx> {
@@ -22,27 +26,163 @@ pub(crate) fn extract_variables(ctx: &Ctx) -> Result<VariablesFromCtx> {
     ctx_variables.insert("ffizer_src_rev", ctx.cmd_opt.src.rev.clone())?;
     ctx_variables.insert("ffizer_src_subfolder", ctx.cmd_opt.src.subfolder.clone())?;
     ctx_variables.insert("ffizer_version", env!("CARGO_PKG_VERSION"))?;
+    let dst_folder = &ctx.cmd_opt.dst_folder;
+    ctx_variables.insert(
+        "ffizer_dst_folder_name",
+        dst_folder
+            .canonicalize()
+            .or_else(|_| std::path::absolute(dst_folder))
+            .ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string())),
+    )?;
+    let git_config = |key: &str| crate::git::find_config_string(dst_folder, key).ok();
+    ctx_variables.insert("ffizer_git_user_name", git_config("user.name"))?;
+    ctx_variables.insert("ffizer_git_user_email", git_config("user.email"))?;
+    ctx_variables.insert("ffizer_dst_git_remote_url", git_config("remote.origin.url"))?;
+    let (year, month, day) = today();
+    ctx_variables.insert("ffizer_date", format!("{year:04}-{month:02}-{day:02}"))?;
+    ctx_variables.insert("ffizer_year", year)?;
+    ctx_variables.insert("ffizer_os", std::env::consts::OS)?;
+    ctx_variables.insert("ffizer_arch", std::env::consts::ARCH)?;
 
     Ok(VariablesFromCtx {
         src: ctx_variables,
         cli: get_cli_variables(ctx)?,
         saved: get_saved_variables(&ctx.cmd_opt.dst_folder)?,
+        user: ctx.user_cfg.find_variables()?,
     })
 }
 
+/// current date (UTC) as (year, month, day)
+fn today() -> (i64, u32, u32) {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    civil_from_days((secs / 86_400) as i64)
+}
+
+/// convert a number of days since 1970-01-01 into (year, month, day)
+/// based on http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// name of the environment variable that could provide the value of the variable `name`
+/// (eg `FFIZER_VAR_PROJECT_NAME` for `project_name`)
+pub(crate) fn env_var_name(name: &str) -> String {
+    let suffix = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    format!("FFIZER_VAR_{}", suffix)
+}
+
+/// values of the variables provided by the environment variables `FFIZER_VAR_<NAME>`
+pub(crate) fn get_env_variables(variable_cfgs: &[VariableCfg]) -> Result<Variables> {
+    // keys like `a-b`, `a_b` and `a.b` share the same environment variable
+    let mut keys_by_env_name: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for variable_cfg in variable_cfgs {
+        let key = variable_cfg.key();
+        keys_by_env_name
+            .entry(env_var_name(&key))
+            .or_default()
+            .insert(key);
+    }
+    let mut variables = Variables::default();
+    for variable_cfg in variable_cfgs.iter().filter(|cfg| !cfg.fixed) {
+        let key = variable_cfg.key();
+        let env_name = env_var_name(&key);
+        if let Ok(v) = std::env::var(&env_name) {
+            let keys = &keys_by_env_name[&env_name];
+            if keys.len() > 1 {
+                return Err(Error::AmbiguousEnvVariable {
+                    env_name,
+                    keys: keys.iter().cloned().collect(),
+                });
+            }
+            variables.insert(key, value_from_env(variable_cfg, &v)?)?;
+        }
+    }
+    Ok(variables)
+}
+
+/// value of a variable provided by an environment variable (a secret is kept verbatim, as a string)
+pub(crate) fn value_from_env(variable_cfg: &VariableCfg, v: &str) -> Result<serde_yaml::Value> {
+    if variable_cfg.is_secret() {
+        Ok(serde_yaml::Value::String(v.to_owned()))
+    } else {
+        value_from_input(v)
+    }
+}
+
+/// value of a variable provided as text by the user (cli, environment)
+pub(crate) fn value_from_input(v: &str) -> Result<serde_yaml::Value> {
+    let v = match v.to_lowercase().trim() {
+        "true" | "y" | "yes" => "true",
+        "false" | "n" | "no" => "false",
+        _ => v.trim(),
+    };
+    Variables::value_from_str(v)
+}
+
 fn get_cli_variables(ctx: &Ctx) -> Result<Variables> {
     let mut variables = Variables::default();
     ctx.cmd_opt
         .key_value
         .iter()
-        .map(|(k, v)| {
-            let v = match v.to_lowercase().trim() {
-                "true" | "y" | "yes" => "true",
-                "false" | "n" | "no" => "false",
-                _ => v.trim(),
-            };
-            variables.insert(k, Variables::value_from_str(v)?)
-        })
+        .map(|(k, v)| variables.insert(k, value_from_input(v)?))
         .collect::<Result<Vec<()>>>()?;
     Ok(variables)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0, (1970, 1, 1))]
+    #[case(59, (1970, 3, 1))]
+    #[case(11_016, (2000, 2, 29))]
+    #[case(19_723, (2024, 1, 1))]
+    #[case(20_742, (2026, 10, 16))]
+    #[case(-1, (1969, 12, 31))]
+    fn test_civil_from_days(#[case] days: i64, #[case] expected: (i64, u32, u32)) {
+        assert_eq!(civil_from_days(days), expected);
+    }
+
+    #[rstest]
+    #[case(" yes ", false, serde_yaml::Value::Bool(true))]
+    #[case(" yes ", true, serde_yaml::Value::String(" yes ".to_owned()))]
+    #[case("n", true, serde_yaml::Value::String("n".to_owned()))]
+    #[case("42", true, serde_yaml::Value::String("42".to_owned()))]
+    fn test_value_from_env(
+        #[case] input: &str,
+        #[case] secret: bool,
+        #[case] expected: serde_yaml::Value,
+    ) {
+        let variable_cfg = VariableCfg {
+            name: "token".to_owned(),
+            secret: Some(secret),
+            ..Default::default()
+        };
+        assert_eq!(value_from_env(&variable_cfg, input).unwrap(), expected);
+    }
+}
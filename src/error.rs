@@ -36,6 +36,9 @@ pub enum Error {
     #[error("try to find git config '{key:?}'")]
     GitFindConfig { key: String, source: GitError },
 
+    #[error("try to find the commit checked out into {path:?}")]
+    GitFindCommit { path: PathBuf, source: GitError },
+
     #[error("canonicalize {path:?}")]
     CanonicalizePath {
         path: PathBuf,
@@ -48,6 +51,11 @@ pub enum Error {
     },
     #[error("create temp folder")]
     CreateTmpFolder { source: std::io::Error },
+    #[error("extract archive {path:?}")]
+    ExtractArchive {
+        path: PathBuf,
+        source: std::io::Error,
+    },
     #[error("remove folder {path:?}")]
     RemoveFolder {
         path: PathBuf,
@@ -104,8 +112,8 @@ pub enum Error {
         source: globset::Error,
     },
 
-    #[error("fail to parse string as uri for git repo '{value:?}'")]
-    ParseGitUri { value: String, source: regex::Error },
+    #[error("fail to parse '{value}' as uri of template: {reason}")]
+    ParseSourceUri { value: String, reason: String },
 
     #[error("local path({path:?}) not found for uri({uri:?}) subfolder({subfolder:?})")]
     LocalPathNotFound {
@@ -123,6 +131,52 @@ pub enum Error {
     #[error("failed to parse value '{value}' for variable '{name}'")]
     ReadVariable { name: String, value: String },
 
+    #[error(
+        "missing value for required variable(s) in no-interaction mode:\n{}",
+        format_missing_variables(.names)
+    )]
+    MissingRequiredVariables { names: Vec<String> },
+
+    #[error("cycle into the dependencies of variables: {}", .names.join(" -> "))]
+    VariablesDependencyCycle { names: Vec<String> },
+
+    #[error("cycle into the imports of templates: {}", .locs.join(" -> "))]
+    ImportsCycle { locs: Vec<String> },
+
+    #[error(
+        "can not store the variable '{key}', '{parent}' is already a variable (not a namespace)"
+    )]
+    VariableNotANamespace { key: String, parent: String },
+
+    #[error(
+        "environment variable '{env_name}' is ambiguous, it could provide the variables: {}",
+        .keys.join(", ")
+    )]
+    AmbiguousEnvVariable { env_name: String, keys: Vec<String> },
+
+    #[error(
+        "conflicting overrides of the variable '{name}' of the template {loc} (imported several times)"
+    )]
+    ConflictingVariableOverrides { name: String, loc: String },
+
+    #[error("destination path '{value}' should be a relative path inside the destination")]
+    InvalidDstPath { value: String },
+
+    #[error("cache folder {path:?} should be a folder inside the cache")]
+    InvalidCachePath { path: PathBuf },
+
+    #[error("template {loc} is not locked into `.ffizer/lock.yaml` (reapply without `--locked`)")]
+    TemplateNotLocked { loc: String },
+
+    #[error("fail to read the user configuration {path:?}")]
+    ReadUserCfg {
+        path: PathBuf,
+        source: serde_yaml::Error,
+    },
+
+    #[error("unknown option '{name}' into the defaults of the user configuration")]
+    UnknownUserCfgOption { name: String },
+
     #[error(transparent)]
     // #[error("fail to process io")]
     Io {
@@ -149,6 +203,12 @@ pub enum Error {
         script: String,
         source: run_script::ScriptError,
     },
+    #[error("script '{script}' failed with exit code {code}: {stderr}")]
+    ScriptFailed {
+        script: String,
+        code: i32,
+        stderr: String,
+    },
     #[error(transparent)]
     SerdeJson {
         #[from]
@@ -171,6 +231,19 @@ pub enum Error {
     },
 }
 
+fn format_missing_variables(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| {
+            format!(
+                "  - {name}: set it with `--variables {name}=<value>` or the environment variable `{}`",
+                crate::ctx::env_var_name(name)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 impl From<serde_yaml::Error> for Error {
     fn from(source: serde_yaml::Error) -> Self {
         Error::SerdeYaml {
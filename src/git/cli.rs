@@ -19,32 +19,112 @@ pub enum GitCliError {
 pub(super) fn retrieve(dst: &Path, url: &str, rev: &Option<String>) -> Result<(), GitError> {
     if dst.join(".git").exists() {
         debug!("Repository already exists, update it");
-        git_cmd(dst, &["reset", "--hard" /*, &format!("origin/{rev}")*/])?;
-        git_cmd(dst, &["pull", "--force", "--prune"])?;
+        // a commit is immutable, no need to fetch when it's already present
+        let has_commit = rev
+            .as_deref()
+            .is_some_and(|rev| super::is_full_sha(rev) && has_commit(dst, rev));
+        if !has_commit {
+            // the url could have changed since the clone (eg a new mirror)
+            git_cmd_checked(dst, &["remote", "set-url", "origin", url])?;
+            git_cmd_checked(
+                dst,
+                &[
+                    "fetch",
+                    "-q",
+                    "--force",
+                    "--prune",
+                    "--tags",
+                    "origin",
+                    "+refs/heads/*:refs/remotes/origin/*",
+                ],
+            )?;
+        }
     } else {
         debug!("Repository does not exists, create it");
         fs::create_dir_all(dst).map_err(|source| GitError::CreateFolder {
             path: dst.to_path_buf(),
             source,
         })?;
-        git_cmd(dst, &["clone", "-q", url, dst.to_str().unwrap_or_default()])?;
-        if let Some(rev) = rev {
-            git_cmd(dst, &["checkout", rev])?;
+        git_cmd_checked(dst, &["clone", "-q", url, dst.to_str().unwrap_or_default()])?;
+    }
+    // resolve the rev as a branch of the remote, else as a tag or a (abbreviated) sha
+    let target = match rev {
+        None => "origin/HEAD".to_owned(),
+        Some(rev) if has_commit(dst, &format!("refs/remotes/origin/{rev}")) => {
+            format!("origin/{rev}")
         }
+        Some(rev) => rev.to_owned(),
+    };
+    git_cmd_checked(dst, &["checkout", "-q", "--force", "--detach", &target])?;
+    Ok(())
+}
+
+fn git_cmd_checked(dst: &Path, args: &[&str]) -> Result<(), GitError> {
+    let status = git_cmd(dst, args)?;
+    if status.success() {
+        Ok(())
+    } else {
+        let msg = format!("git {}", args.join(" "));
+        Err(GitCliError::CommandError(msg, status).into())
     }
+}
 
-    Ok(())
+fn has_commit(dst: &Path, rev: &str) -> bool {
+    process::Command::new("git")
+        .args(["rev-parse", "--verify", "-q", &format!("{rev}^{{commit}}")])
+        .current_dir(dst)
+        .output()
+        .is_ok_and(|output| output.status.success())
 }
 
 pub fn find_cmd_tool(kind: &str) -> Result<String, GitError> {
-    let tool = config_get_string(&format!("{}.tool", kind))?;
-    let result = config_get_string(&format!("{}tool.{}.cmd", kind, tool))?;
+    let current_dir = Path::new(".");
+    let tool = config_get_string(current_dir, &format!("{}.tool", kind))?;
+    let result = config_get_string(current_dir, &format!("{}tool.{}.cmd", kind, tool))?;
     Ok(result)
 }
 
-fn config_get_string(value: &str) -> Result<String, GitCliError> {
+pub fn find_head_commit(path: &Path) -> Result<String, GitError> {
+    let output = process::Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(path)
+        .output()?;
+    let status = output.status;
+    if status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(GitCliError::CommandError("git rev-parse HEAD".to_owned(), status).into())
+    }
+}
+
+/// the `url.<base>.insteadOf` rules of the configuration, as (prefix, base)
+pub fn find_instead_of_rules() -> Result<Vec<(String, String)>, GitError> {
+    let args = ["config", "--get-regexp", r"^url\..*\.insteadof$"];
+    let output = process::Command::new("git").args(args).output()?;
+    // exit code 1 when there is no rule
+    if !output.status.success() && output.status.code() != Some(1) {
+        let msg = format!("git {}", args.join(" "));
+        return Err(GitCliError::CommandError(msg, output.status).into());
+    }
+    let rules = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter_map(|(name, prefix)| {
+            let base = name.strip_prefix("url.")?.strip_suffix(".insteadof")?;
+            Some((prefix.to_owned(), base.to_owned()))
+        })
+        .collect();
+    Ok(rules)
+}
+
+pub fn find_config_string(path: &Path, key: &str) -> Result<String, GitError> {
+    Ok(config_get_string(path, key)?)
+}
+
+fn config_get_string(current_dir: &Path, value: &str) -> Result<String, GitCliError> {
     let output = process::Command::new("git")
         .args(["config", value])
+        .current_dir(current_dir)
         .output()?;
     let status = output.status;
     if status.success() {
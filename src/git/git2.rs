@@ -1,37 +1,81 @@
 use crate::error::*;
-use git2::build::RepoBuilder;
-use git2::{Config, FetchOptions};
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{Config, FetchOptions, Object, ObjectType, Repository, ResetType};
 use std::path::Path;
-use std::time::{Duration, SystemTime};
 use tracing::{info, warn};
 
 use super::GitError;
 
-/// clone a repository at a rev to a directory
-// TODO if the directory is already present then fetch and rebase (if not in offline mode)
+/// clone a repository at a rev to a directory,
+/// if the repository is already present then fetch and reset it to the rev
 #[tracing::instrument]
 pub fn retrieve(dst: &Path, url: &str, rev: &Option<String>) -> Result<(), GitError> {
     let fo = make_fetch_options()?;
-    if dst.exists() {
-        // HACK until pull is fixed, remove cache if older than ttl (5min) and clone
-        if dst
-            .metadata()
-            .ok()
-            .and_then(|m| m.modified().ok())
-            .and_then(|t| t.duration_since(SystemTime::now()).ok())
-            .map(|d| d > Duration::from_secs(5 * 60))
-            .unwrap_or(true)
-        {
+    if dst.join(".git").exists() {
+        info!("git fetch into cached template");
+        update(dst, url, rev, fo)?;
+    } else {
+        if dst.exists() {
+            // not a repository (eg interrupted clone)
             std::fs::remove_dir_all(dst)?;
-            clone(dst, url, rev, fo)?;
         }
-    } else {
         info!("git clone into cached template");
         clone(dst, url, rev, fo)?;
     }
     Ok(())
 }
 
+fn update(
+    dst: &Path,
+    url: &str,
+    rev: &Option<String>,
+    mut fo: FetchOptions<'_>,
+) -> Result<(), GitError> {
+    let repo = Repository::open(dst)?;
+    // a commit is immutable, no need to fetch when it's already present
+    let has_commit = rev
+        .as_deref()
+        .filter(|rev| super::is_full_sha(rev))
+        .is_some_and(|rev| repo.revparse_single(rev).is_ok());
+    if !has_commit {
+        // the url could have changed since the clone (eg a new mirror)
+        repo.remote_set_url("origin", url)?;
+        repo.find_remote("origin")?.fetch(
+            &[
+                "+refs/heads/*:refs/remotes/origin/*",
+                "+refs/tags/*:refs/tags/*",
+            ],
+            Some(&mut fo),
+            None,
+        )?;
+    }
+    checkout(&repo, rev)
+}
+
+/// checkout (detached) the commit of the rev, or of the default branch of the remote
+fn checkout(repo: &Repository, rev: &Option<String>) -> Result<(), GitError> {
+    let target = resolve_rev(repo, rev)?;
+    repo.set_head_detached(target.id())?;
+    repo.reset(
+        &target,
+        ResetType::Hard,
+        Some(CheckoutBuilder::new().force()),
+    )?;
+    Ok(())
+}
+
+/// resolve the rev as a branch of the remote, else as a tag, else as a (abbreviated) sha
+fn resolve_rev<'r>(repo: &'r Repository, rev: &Option<String>) -> Result<Object<'r>, GitError> {
+    let object = match rev {
+        None => repo.revparse_single("refs/remotes/origin/HEAD")?,
+        Some(rev) => repo
+            .revparse_single(&format!("refs/remotes/origin/{}", rev))
+            .or_else(|_| repo.revparse_single(&format!("refs/tags/{}", rev)))
+            .or_else(|_| repo.revparse_single(rev))?,
+    };
+    Ok(object.peel(ObjectType::Commit)?)
+}
+
 /// a best attempt effort is made to authenticate
 /// requests when required to support private
 /// git repositories
@@ -61,13 +105,11 @@ fn clone(
         path: dst.to_path_buf(),
         source,
     })?;
-    let mut builder = RepoBuilder::new();
-    if let Some(rev) = rev {
-        builder.branch(rev);
-    }
-    builder
+    RepoBuilder::new()
         .fetch_options(fo)
         .clone(url.as_ref(), dst.as_ref())
+        .map_err(GitError::from)
+        .and_then(|repo| checkout(&repo, rev))
         .inspect_err(|_| {
             // remove dst folder on error
             let _ = std::fs::remove_dir_all(dst);
@@ -75,6 +117,39 @@ fn clone(
     Ok(())
 }
 
+/// the configuration of the repository containing `path` (if any) else the default configuration
+pub fn find_config_string(path: &Path, key: &str) -> Result<String, git2::Error> {
+    let config = match git2::Repository::discover(path) {
+        Ok(repo) => repo.config()?,
+        Err(_) => Config::open_default()?,
+    };
+    config.get_string(key)
+}
+
+pub fn find_head_commit(path: &Path) -> Result<String, git2::Error> {
+    let repo = Repository::discover(path)?;
+    let commit = repo.head()?.peel_to_commit()?;
+    Ok(commit.id().to_string())
+}
+
+/// the `url.<base>.insteadOf` rules of the default configuration, as (prefix, base)
+pub fn find_instead_of_rules() -> Result<Vec<(String, String)>, git2::Error> {
+    let config = Config::open_default()?;
+    let mut rules = vec![];
+    let mut entries = config.entries(Some(r"^url\..*\.insteadof$"))?;
+    while let Some(entry) = entries.next() {
+        let entry = entry?;
+        let base = entry
+            .name()?
+            .strip_prefix("url.")
+            .and_then(|name| name.strip_suffix(".insteadof"));
+        if let Some(base) = base {
+            rules.push((entry.value()?.to_owned(), base.to_owned()));
+        }
+    }
+    Ok(rules)
+}
+
 /// kind can be "merge" or "diff"
 pub fn find_cmd_tool(kind: &str) -> Result<String, git2::Error> {
     let config = Config::open_default()?;
@@ -92,14 +167,13 @@ mod tests {
     //#[cfg(not(target_os = "windows"))]
     #[test_trace::test]
     fn retrieve_should_update_existing_template() {
-        if std::process::Command::new("git")
-            .arg("version")
-            .output()
-            .is_err()
-        {
-            eprintln!("skip the test because `git` is not installed");
-            return;
-        }
+        assert!(
+            std::process::Command::new("git")
+                .arg("version")
+                .output()
+                .is_ok(),
+            "the test requires `git` to be installed"
+        );
 
         let tmp_dir = tempdir().unwrap();
 
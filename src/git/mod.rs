@@ -1,9 +1,10 @@
 use std::env::current_dir;
 use std::path::{Path, PathBuf};
 use std::process::ExitStatus;
+use std::time::{Duration, SystemTime};
 use std::{io, process};
 
-use tracing::{error, info};
+use tracing::{debug, error, info};
 
 use crate::error::Error;
 
@@ -49,7 +50,7 @@ where
     U: AsRef<str>,
 {
     let dst = dst.as_ref();
-    let url = url.as_ref();
+    let url = &rewrite_url(url.as_ref());
 
     #[cfg(feature = "git2")]
     match self::git2::retrieve(dst, url, rev) {
@@ -77,6 +78,83 @@ where
     })
 }
 
+/// environment variable with the mirrors of the remote templates, as `<prefix>=<mirror>` separated by `;`
+/// (eg `https://github.com/=https://mirror.example.com/github/`)
+pub const GIT_MIRROR_ENV: &str = "FFIZER_GIT_MIRROR";
+
+/// the url to fetch, rewritten by the mirrors of `FFIZER_GIT_MIRROR`,
+/// else by the `url.<base>.insteadOf` rules of the git configuration
+pub fn rewrite_url(url: &str) -> String {
+    let mirrors = std::env::var(GIT_MIRROR_ENV)
+        .map(|v| parse_mirrors(&v))
+        .unwrap_or_default();
+    let rewritten = rewrite_url_with(url, &mirrors).or_else(|| {
+        let rules = find_instead_of_rules()
+            .inspect_err(|e| debug!("fail to read the insteadOf rules of git: {e:?}"))
+            .unwrap_or_default();
+        rewrite_url_with(url, &rules)
+    });
+    match rewritten {
+        Some(rewritten) => {
+            info!(url, rewritten, "rewrite url");
+            rewritten
+        }
+        None => url.to_owned(),
+    }
+}
+
+/// replace the longest matching prefix of the rules (prefix, replacement)
+fn rewrite_url_with(url: &str, rules: &[(String, String)]) -> Option<String> {
+    rules
+        .iter()
+        .filter(|(prefix, _)| url.starts_with(prefix.as_str()))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(prefix, replacement)| format!("{}{}", replacement, &url[prefix.len()..]))
+}
+
+fn parse_mirrors(value: &str) -> Vec<(String, String)> {
+    value
+        .split(';')
+        .filter_map(|rule| rule.trim().split_once('='))
+        .map(|(prefix, mirror)| (prefix.to_owned(), mirror.to_owned()))
+        .collect()
+}
+
+fn find_instead_of_rules() -> Result<Vec<(String, String)>, GitError> {
+    #[cfg(feature = "git2")]
+    match self::git2::find_instead_of_rules() {
+        Ok(s) => {
+            return Ok(s);
+        }
+        Err(e) => {
+            debug!("Oops, fail with git2: {e:?}");
+        }
+    }
+
+    // Fallback to cli (fails if git is not installed)
+    cli::find_instead_of_rules()
+}
+
+/// is `rev` a full sha of commit (immutable, unlike a branch or a tag)
+fn is_full_sha(rev: &str) -> bool {
+    rev.len() == 40 && rev.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// is the repository at `dst` cloned or fetched less than `ttl` ago
+pub fn is_recently_fetched(dst: &Path, ttl: Duration) -> bool {
+    find_last_fetch_time(dst)
+        .and_then(|t| SystemTime::now().duration_since(t).ok())
+        .is_some_and(|age| age < ttl)
+}
+
+/// when the repository at `dst` was cloned or fetched for the last time
+pub fn find_last_fetch_time(dst: &Path) -> Option<SystemTime> {
+    let git_dir = dst.join(".git");
+    [git_dir.join("FETCH_HEAD"), git_dir]
+        .iter()
+        .find_map(|p| p.metadata().and_then(|m| m.modified()).ok())
+}
+
 pub fn find_cmd_tool(kind: &str) -> Result<String, GitError> {
     #[cfg(feature = "git2")]
     match self::git2::find_cmd_tool(kind) {
@@ -96,6 +174,45 @@ pub fn find_cmd_tool(kind: &str) -> Result<String, GitError> {
     }
 }
 
+/// the sha of the commit checked out into the repository containing `path`
+pub fn find_head_commit(path: &Path) -> Result<String, GitError> {
+    #[cfg(feature = "git2")]
+    match self::git2::find_head_commit(path) {
+        Ok(s) => {
+            return Ok(s);
+        }
+        Err(e) => {
+            debug!("Oops, fail with git2: {e:?}");
+        }
+    }
+
+    // Fallback to cli (fails if git is not installed)
+    cli::find_head_commit(path)
+}
+
+/// read the value of `key` from the git configuration of the repository containing `path`
+/// (or from the global configuration when `path` is not into a repository)
+pub fn find_config_string(path: &Path, key: &str) -> Result<String, GitError> {
+    // the path could not exist yet (eg destination folder)
+    let path = path
+        .ancestors()
+        .find(|p| p.is_dir())
+        .unwrap_or_else(|| Path::new("."));
+
+    #[cfg(feature = "git2")]
+    match self::git2::find_config_string(path, key) {
+        Ok(s) => {
+            return Ok(s);
+        }
+        Err(e) => {
+            debug!("Oops, fail with git2: {e:?}");
+        }
+    }
+
+    // Fallback to cli (fails if git is not installed)
+    cli::find_config_string(path, key)
+}
+
 fn git_cmd(current_dir: &Path, args: &[&str]) -> Result<ExitStatus, GitError> {
     info!(
         "Running command `git {}` in {current_dir:?}",
@@ -118,6 +235,43 @@ mod tests {
     use tempfile::tempdir;
     use tracing::warn;
 
+    fn rules(rules: &[(&str, &str)]) -> Vec<(String, String)> {
+        rules
+            .iter()
+            .map(|(p, r)| (p.to_string(), r.to_string()))
+            .collect()
+    }
+
+    #[rstest::rstest]
+    #[case(
+        "https://github.com/ffizer/ffizer",
+        Some("https://mirror.example.com/github/ffizer/ffizer")
+    )]
+    #[case("https://github.com/foo/bar", Some("/srv/mirrors/foo/bar"))]
+    #[case("git@github.com:ffizer/ffizer", None)]
+    #[case("https://gitlab.com/ffizer/ffizer", None)]
+    fn test_rewrite_url_with(#[case] url: &str, #[case] expected: Option<&str>) {
+        // the longest matching prefix wins
+        let rules = rules(&[
+            ("https://github.com/", "https://mirror.example.com/github/"),
+            ("https://github.com/foo/", "/srv/mirrors/foo/"),
+        ]);
+        assert_eq!(rewrite_url_with(url, &rules).as_deref(), expected);
+    }
+
+    #[test]
+    fn test_parse_mirrors() {
+        assert_eq!(
+            parse_mirrors(
+                "https://github.com/=file:///srv/github/; git@gitlab.com:=/srv/gitlab/;invalid"
+            ),
+            rules(&[
+                ("https://github.com/", "file:///srv/github/"),
+                ("git@gitlab.com:", "/srv/gitlab/"),
+            ])
+        );
+    }
+
     #[test]
     #[ignore = "Only works on my laptop"]
     fn should_get_merge_cmd() {
@@ -125,12 +279,21 @@ mod tests {
         assert_eq!(result, "code --wait $MERGED");
     }
 
+    #[test]
+    fn test_is_recently_fetched() {
+        let tmp_dir = tempdir().unwrap();
+        assert!(!is_recently_fetched(
+            tmp_dir.path(),
+            Duration::from_secs(60)
+        ));
+        fs::create_dir_all(tmp_dir.path().join(".git")).unwrap();
+        assert!(is_recently_fetched(tmp_dir.path(), Duration::from_secs(60)));
+        assert!(!is_recently_fetched(tmp_dir.path(), Duration::ZERO));
+    }
+
     #[test_trace::test]
     fn retrieve_should_update_existing_template() {
-        if !has_git_cli() {
-            eprintln!("skip the test because `git` is not installed");
-            return;
-        }
+        assert!(has_git_cli(), "the test requires `git` to be installed");
 
         let tmp_dir = tempdir().unwrap();
 
@@ -158,6 +321,88 @@ mod tests {
         );
     }
 
+    #[test_trace::test]
+    fn retrieve_should_checkout_tag_and_sha() {
+        assert!(has_git_cli(), "the test requires `git` to be installed");
+        check_retrieve_tag_and_sha(|dst, url, rev| retrieve(dst, url, rev).map_err(Into::into));
+    }
+
+    #[test_trace::test]
+    fn cli_retrieve_should_checkout_tag_and_sha() {
+        assert!(has_git_cli(), "the test requires `git` to be installed");
+        check_retrieve_tag_and_sha(|dst, url, rev| {
+            cli::retrieve(dst, url, rev).map_err(|e| e.to_string().into())
+        });
+    }
+
+    fn check_retrieve_tag_and_sha<F>(retrieve: F)
+    where
+        F: Fn(&Path, &str, &Option<String>) -> Result<(), Box<dyn std::error::Error>>,
+    {
+        let tmp_dir = tempdir().unwrap();
+        let src_path = tmp_dir.path().join("src");
+        let (code, output, error) = run_script::run(
+            &format!(
+                r#"
+                    mkdir -p {src_path:?}
+                    cd {src_path:?}
+                    git init -b master
+                    git config user.email "test@example.com"
+                    git config user.name "Test Name"
+                    echo "v1" > foo.txt
+                    git add foo.txt
+                    git commit -m "v1"
+                    git tag v1.0.0
+                    echo "v2" > foo.txt
+                    git commit -am "v2"
+                    git rev-parse HEAD > ../v2.sha
+                    echo "v3" > foo.txt
+                    git commit -am "v3"
+                    "#
+            ),
+            &vec![],
+            &ScriptOptions::new(),
+        )
+        .unwrap();
+        if code != 0 {
+            warn!(%output, %error);
+        }
+        assert_eq!(code, 0, "setup template");
+        let url = src_path.to_str().unwrap();
+        let sha = fs::read_to_string(tmp_dir.path().join("v2.sha")).unwrap();
+        let sha = sha.trim();
+
+        let cases = [
+            ("tag", Some("v1.0.0"), "v1\n"),
+            ("sha", Some(sha), "v2\n"),
+            ("abbreviated_sha", Some(&sha[..8]), "v2\n"),
+            ("branch", Some("master"), "v3\n"),
+            ("default", None, "v3\n"),
+        ];
+        for (name, rev, expected) in cases {
+            let rev = rev.map(str::to_owned);
+            // fresh clone
+            let dst_path = tmp_dir.path().join(name);
+            retrieve(&dst_path, url, &rev).unwrap();
+            assert_eq!(
+                fs::read_to_string(dst_path.join("foo.txt")).unwrap(),
+                expected,
+                "clone with {name}"
+            );
+            // update of a clone at an other rev
+            let dst_path = tmp_dir.path().join("shared");
+            retrieve(&dst_path, url, &rev).unwrap();
+            assert_eq!(
+                fs::read_to_string(dst_path.join("foo.txt")).unwrap(),
+                expected,
+                "update with {name}"
+            );
+            if rev.as_deref().is_some_and(|rev| sha.starts_with(rev)) {
+                assert_eq!(find_head_commit(&dst_path).unwrap(), sha);
+            }
+        }
+    }
+
     #[tracing::instrument]
     fn template_v1(src_path: &Path, dst_path: &Path, args: &Vec<String>, options: &ScriptOptions) {
         let (code, output, error) = run_script::run(
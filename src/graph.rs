@@ -1,16 +1,18 @@
+use crate::Result;
+
 pub trait Graph {
     type K: Clone + Eq;
     type V;
 
     fn find_node(&self, k: &Self::K) -> Option<&Self::V>;
-    fn find_edges_direct(&self, v: &Self::V) -> Vec<Self::K>;
-    fn find_edges_ordered_by_depth(&self, root_key: &Self::K) -> Vec<Self::K> {
+    fn find_edges_direct(&self, v: &Self::V) -> Result<Vec<Self::K>>;
+    fn find_edges_ordered_by_depth(&self, root_key: &Self::K) -> Result<Vec<Self::K>> {
         let mut back = vec![root_key.clone()];
         let mut visited = 0;
         while visited < back.len() {
             let k = back.get(visited).expect("should be present");
             if let Some(v) = self.find_node(k) {
-                for child in self.find_edges_direct(v) {
+                for child in self.find_edges_direct(v)? {
                     if !back.contains(&child) {
                         back.push(child.clone())
                     }
@@ -18,7 +20,7 @@ pub trait Graph {
             }
             visited += 1;
         }
-        back
+        Ok(back)
     }
 }
 
@@ -38,8 +40,8 @@ mod tests {
         fn find_node(&self, k: &Self::K) -> Option<&Self::V> {
             self.datas.get(k)
         }
-        fn find_edges_direct(&self, v: &Self::V) -> Vec<Self::K> {
-            v.clone()
+        fn find_edges_direct(&self, v: &Self::V) -> Result<Vec<Self::K>> {
+            Ok(v.clone())
         }
     }
     #[test]
@@ -77,7 +79,7 @@ mod tests {
         ];
         assert_eq!(
             &expected,
-            &(g.find_edges_ordered_by_depth(&"k1".to_owned()))
+            &(g.find_edges_ordered_by_depth(&"k1".to_owned()).unwrap())
         );
     }
 }
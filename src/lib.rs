@@ -6,6 +6,8 @@ extern crate serde;
 pub mod error;
 pub mod tools;
 
+mod archive;
+mod cache;
 mod cfg;
 mod cli_opt;
 mod ctx;
@@ -19,6 +21,7 @@ mod source_loc;
 mod source_uri;
 mod timeline;
 mod ui;
+mod user_cfg;
 mod variable_def;
 mod variables;
 
@@ -27,8 +30,9 @@ pub use crate::cli_opt::*;
 pub use crate::path_pattern::PathPattern;
 pub use crate::source_loc::SourceLoc;
 pub use crate::source_uri::SourceUri;
+pub use crate::user_cfg::UserCfg;
 
-use crate::cfg::{TemplateComposite, VariableValueCfg, render_composite};
+use crate::cfg::{LayerScope, TemplateComposite, VariableValueCfg, render_composite};
 use crate::error::*;
 use crate::files::ChildPath;
 use crate::source_file::{SourceFile, SourceFileMetadata};
@@ -61,12 +65,19 @@ pub struct Action {
 #[derive(Debug, Clone, Default)]
 pub struct Ctx {
     pub cmd_opt: ApplyOpts,
+    /// configuration of the user (default values of variables)
+    pub user_cfg: UserCfg,
 }
 
-pub fn reprocess(cmd_opt: ReapplyOpts) -> Result<()> {
+pub fn reprocess(cmd_opt: ReapplyOpts, user_cfg: UserCfg) -> Result<()> {
     let temp_dir = TempDir::with_prefix(IGNORED_FOLDER_PREFIX)?;
 
     let tmp_template = timeline::make_template_from_folder(&cmd_opt.dst_folder, temp_dir.path())?;
+    let locked_commits = if cmd_opt.locked {
+        Some(timeline::get_locked_commits(&cmd_opt.dst_folder)?)
+    } else {
+        None
+    };
     let new_ctx = Ctx {
         cmd_opt: ApplyOpts {
             confirm: cmd_opt.confirm,
@@ -74,38 +85,148 @@ pub fn reprocess(cmd_opt: ReapplyOpts) -> Result<()> {
             update_mode: cmd_opt.update_mode,
             no_interaction: cmd_opt.no_interaction,
             dst_folder: cmd_opt.dst_folder,
-            offline: cmd_opt.offline,
+            fetch: FetchOpts {
+                refresh: cmd_opt.fetch.refresh || cmd_opt.update,
+                locked_commits,
+                ..cmd_opt.fetch
+            },
             key_value: cmd_opt.key_value,
+            preset: None,
+            list_variables: false,
         },
+        user_cfg,
     };
     process(&new_ctx)?;
     Ok(temp_dir.close()?)
 }
 
+pub fn show_imports(cmd_opt: ShowImportsOpts) -> Result<()> {
+    let ctx = Ctx {
+        cmd_opt: ApplyOpts {
+            src: cmd_opt.src,
+            fetch: cmd_opt.fetch,
+            key_value: cmd_opt.key_value,
+            dst_folder: PathBuf::from("."),
+            no_interaction: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut variables = ctx::extract_variables(&ctx)?;
+    variables.src.append(&mut variables.cli);
+    let template_composite =
+        TemplateComposite::from_src(&variables.src, &ctx.cmd_opt.fetch, &ctx.cmd_opt.src)?;
+    ui::show_imports(&template_composite.find_imports_tree()?)
+}
+
+pub fn manage_cache(cmd_opt: CacheOpts) -> Result<()> {
+    let cache_folder = SourceLoc::find_cache_folder()?;
+    match cmd_opt.cmd {
+        CacheCommand::List => {
+            ui::show_cache_entries(&cache_folder, &cache::find_entries(&cache_folder)?)
+        }
+        CacheCommand::Prune(opts) => {
+            ui::show_removed_cache_entries(&cache::prune(&cache_folder, opts.older_than)?)
+        }
+        CacheCommand::Clear(opts) => {
+            let relative = match &opts.uri {
+                Some(uri) => SourceLoc::find_cache_folder_of(uri)?
+                    .strip_prefix(&cache_folder)?
+                    .to_path_buf(),
+                None => PathBuf::new(),
+            };
+            ui::show_removed_cache_entries(&cache::clear(&cache_folder, &relative)?)
+        }
+    }
+}
+
 pub fn process(ctx: &Ctx) -> Result<()> {
     debug!("extracting variables from context",);
     let mut variables = ctx::extract_variables(ctx)?;
     debug!("compositing templates");
 
     let mut template_composite =
-        TemplateComposite::from_src(&variables.src, ctx.cmd_opt.offline, &ctx.cmd_opt.src)?;
+        TemplateComposite::from_src(&variables.src, &ctx.cmd_opt.fetch, &ctx.cmd_opt.src)?;
+
+    let variable_configs = template_composite.find_variablecfgs()?;
 
-    let mut confirmed_variables = variables.cli;
+    let presets = template_composite.find_presets()?;
+    if ctx.cmd_opt.list_variables {
+        return ui::show_variables(&variable_configs, &presets);
+    }
+    ui::intro("Configure variables")?;
+    let mut confirmed_variables = match ui::select_preset(ctx, &presets, &variables.saved)? {
+        Some(preset) => presets[&preset].clone(),
+        None => Variables::default(),
+    };
+    // cli variables override the ones from environment, that override the ones from preset
+    confirmed_variables.append(&mut ctx::get_env_variables(&variable_configs)?);
+    confirmed_variables.append(&mut variables.cli);
     confirmed_variables.append(&mut variables.src);
-    let confirmed_variables = confirmed_variables; // make immutable
+    // mark secrets before any log of the values
+    variable_configs
+        .iter()
+        .filter(|cfg| cfg.is_secret())
+        .for_each(|cfg| confirmed_variables.mark_secret(cfg.key()));
 
     debug!(confirmed_variables = ?confirmed_variables, "asking variables");
-    let mut variable_configs = template_composite.find_variablecfgs()?;
 
-    // Updates defaults with suggested variables before asking.
-    variable_configs.iter_mut().for_each(|cfg| {
-        if let Some(v) = variables.saved.get(&cfg.name) {
-            cfg.default_value = Some(VariableValueCfg(v.clone()))
+    let mut variable_configs = with_suggested_defaults(variable_configs, &variables)?;
+    let (mut used_variables, mut asked) =
+        ui::ask_variables(ctx, &variable_configs, confirmed_variables.clone())?;
+    loop {
+        // the answers could enable (or disable) conditional imports,
+        // so compose again and ask the variables of the new templates
+        if template_composite.has_conditional_imports() {
+            let recomposed =
+                TemplateComposite::from_src(&used_variables, &ctx.cmd_opt.fetch, &ctx.cmd_opt.src)?;
+            if !recomposed.has_same_layers(&template_composite) {
+                template_composite = recomposed;
+                let configs = template_composite.find_variablecfgs()?;
+                let new_configs = configs
+                    .iter()
+                    .filter(|cfg| !used_variables.contains_key(cfg.key()))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let mut env_variables = ctx::get_env_variables(&new_configs)?;
+                confirmed_variables.append(&mut env_variables.clone());
+                used_variables.append(&mut env_variables);
+                variable_configs = with_suggested_defaults(configs, &variables)?;
+                let (new_variables, new_asked) =
+                    ui::ask_variables(ctx, &variable_configs, used_variables)?;
+                used_variables = new_variables;
+                asked |= new_asked;
+                continue;
+            }
         }
-    });
-    let variable_configs = variable_configs; // make immutable
-
-    let used_variables = ui::ask_variables(ctx, &variable_configs, confirmed_variables)?;
+        // nothing to review when every value was provided (eg via `--variables` or a preset)
+        if !asked {
+            break;
+        }
+        let Some(key) = ui::review_variables(ctx, &variable_configs, &used_variables)? else {
+            break;
+        };
+        // forget the answer and the answers computed from it (except the ones provided by cli)
+        let mut dependents = cfg::find_dependents(&variable_configs, &key)?;
+        dependents.retain(|k| !confirmed_variables.contains_key(k));
+        let mut kept_variables = used_variables.clone();
+        for k in dependents.iter().chain(std::iter::once(&key)) {
+            kept_variables.remove(k);
+            kept_variables.remove(format!("{}__label", k));
+            kept_variables.remove(format!("{}__idx", k));
+        }
+        // ask again with the previous answer as default
+        let mut configs = variable_configs.clone();
+        configs
+            .iter_mut()
+            .filter(|cfg| cfg.key() == key)
+            .for_each(|cfg| {
+                cfg.default_value = used_variables.get(&key).cloned().map(VariableValueCfg);
+                cfg.default_from_cmd = None;
+            });
+        (used_variables, _) = ui::ask_variables(ctx, &configs, kept_variables)?;
+    }
+    let used_variables = used_variables; // make immutable
     // update cfg(s) with variables defined by user (use to update ignore, scripts,...)
     debug!(variables = ?used_variables, "update template_composite with variables");
     template_composite = render_composite(&template_composite, &used_variables, true)?;
@@ -113,11 +234,19 @@ pub fn process(ctx: &Ctx) -> Result<()> {
     let source_files = template_composite.find_sourcefiles()?;
     debug!("defining plan of rendering");
     let actions = plan(ctx, source_files, &used_variables)?;
-    if ui::confirm_plan(ctx, &actions)? {
+    if ui::confirm_plan(ctx, &actions, &used_variables)? {
         debug!("executing plan of rendering");
         execute(ctx, &actions, &used_variables)?;
         debug!("Saving metadata");
-        timeline::save_options(&used_variables, &ctx.cmd_opt.src, &ctx.cmd_opt.dst_folder)?;
+        timeline::save_options(
+            &used_variables,
+            &cfg::find_namespaces(&variable_configs),
+            &ctx.cmd_opt.src,
+            &ctx.cmd_opt.dst_folder,
+        )?;
+        let changes =
+            timeline::save_lock(&template_composite.find_commits(), &ctx.cmd_opt.dst_folder)?;
+        ui::show_updated_templates(&changes)?;
         debug!("running scripts");
         run_scripts(ctx, &template_composite)?;
     }
@@ -125,6 +254,30 @@ pub fn process(ctx: &Ctx) -> Result<()> {
     Ok(())
 }
 
+/// update defaults with suggested values (saved, then environment) before asking
+/// (except for the values fixed by an importing template)
+fn with_suggested_defaults(
+    mut variable_configs: Vec<cfg::VariableCfg>,
+    variables: &ctx::VariablesFromCtx,
+) -> Result<Vec<cfg::VariableCfg>> {
+    for cfg in variable_configs.iter_mut() {
+        if cfg.fixed {
+            continue;
+        }
+        if let Some(v) = variables.saved.get(cfg.key()) {
+            cfg.default_value = Some(VariableValueCfg(v.clone()));
+            cfg.default_from_cmd = None;
+        } else if let Some(v) = cfg.env.as_ref().and_then(|name| std::env::var(name).ok()) {
+            cfg.default_value = Some(VariableValueCfg(ctx::value_from_env(cfg, &v)?));
+            cfg.default_from_cmd = None;
+        } else if let Some(v) = variables.user.get(cfg.key()) {
+            cfg.default_value = Some(VariableValueCfg(v.clone()));
+            cfg.default_from_cmd = None;
+        }
+    }
+    Ok(variable_configs)
+}
+
 fn do_in_folder<F, R>(folder: &Path, f: F) -> Result<R>
 where
     F: FnOnce() -> Result<R>,
@@ -149,7 +302,7 @@ fn plan(ctx: &Ctx, source_files: Vec<SourceFile>, variables: &Variables) -> Resu
     let list_dst_and_src = source_files
         .into_iter()
         .map(|source_file| {
-            compute_dst_path(ctx, source_file.childpath(), variables)
+            compute_dst_path(ctx, source_file.childpath(), &source_file.scope, variables)
                 .map(|dst_path| (dst_path, source_file))
         })
         .collect::<Result<Vec<_>>>()?;
@@ -253,9 +406,15 @@ fn mk_file_on_action(
     a: &Action,
     dest_suffix_ext: &str,
 ) -> Result<(PathBuf, PathBuf)> {
-    let mut variables = variables.clone();
     let dest_full_path_target = PathBuf::from(&a.dst_path);
     let dest_full_path = files::add_suffix(&dest_full_path_target, dest_suffix_ext)?;
+    // the folder is not part of the plan when the file is renamed (`rename` of imports)
+    if let Some(parent) = dest_full_path.parent().filter(|p| !p.exists()) {
+        fs::create_dir_all(parent).map_err(|source| Error::CreateFolder {
+            path: parent.to_path_buf(),
+            source,
+        })?;
+    }
     let mut srcs = a.src.clone();
     srcs.reverse();
     let mut input_content: Vec<u8> = Vec::with_capacity(0);
@@ -265,6 +424,7 @@ fn mk_file_on_action(
     // - [RenderableFile+,RawFile{0,1}]
     for (i, source_file) in srcs.into_iter().enumerate() {
         let src_full_path = PathBuf::from(&source_file.childpath);
+        let mut variables = source_file.scope.view(variables)?;
         match source_file.metadata {
             SourceFileMetadata::RawFile => {
                 if i == index_latest {
@@ -472,7 +632,13 @@ where
 }
 
 //TODO optimize / bench to avoid re-creation of handlebars at each call
-fn compute_dst_path(ctx: &Ctx, src: &ChildPath, variables: &Variables) -> Result<ChildPath> {
+fn compute_dst_path(
+    ctx: &Ctx,
+    src: &ChildPath,
+    scope: &LayerScope,
+    variables: &Variables,
+) -> Result<ChildPath> {
+    let layer_variables = scope.view(variables)?;
     let rendered_relative = src
         .relative
         .to_str()
@@ -484,7 +650,7 @@ fn compute_dst_path(ctx: &Ctx, src: &ChildPath, variables: &Variables) -> Result
                 let s = s.replace('\\', "/"); // HACK: on Windows path with 'folder\{{...}}' becomes 'folder{{...}}'
                 let handlebars = new_hbs();
                 handlebars
-                    .render_template(&s, variables)
+                    .render_template(&s, &layer_variables)
                     .map_err(|source| Error::Handlebars {
                         when: format!("define path for '{:?}'", src),
                         template: Box::new(s),
@@ -497,7 +663,7 @@ fn compute_dst_path(ctx: &Ctx, src: &ChildPath, variables: &Variables) -> Result
 
     Ok(ChildPath {
         base: ctx.cmd_opt.dst_folder.clone(),
-        relative,
+        relative: scope.dst_path(&relative, variables)?,
     })
 }
 
@@ -562,6 +728,7 @@ mod tests {
                 dst_folder: dst.into(),
                 ..Default::default()
             },
+            ..Default::default()
         }
     }
 
@@ -575,7 +742,7 @@ mod tests {
         let variables = new_variables_for_test();
         let src = ChildPath::new("test/src", "hello/sample.txt");
         let expected = ChildPath::new(DST_FOLDER_STR, "hello/sample.txt");
-        let actual = compute_dst_path(&ctx, &src, &variables).unwrap();
+        let actual = compute_dst_path(&ctx, &src, &LayerScope::default(), &variables).unwrap();
         assert_eq!(&expected, &actual);
     }
 
@@ -585,7 +752,7 @@ mod tests {
         let variables = new_variables_for_test();
         let src = ChildPath::new("test/src", "hello/sample.txt.ffizer.hbs");
         let expected = ChildPath::new(DST_FOLDER_STR, "hello/sample.txt");
-        let actual = compute_dst_path(&ctx, &src, &variables).unwrap();
+        let actual = compute_dst_path(&ctx, &src, &LayerScope::default(), &variables).unwrap();
         assert_eq!(&expected, &actual);
     }
 
@@ -596,7 +763,7 @@ mod tests {
 
         let src = ChildPath::new("test/src", "hello/{{ prj }}.txt");
         let expected = ChildPath::new(DST_FOLDER_STR, "hello/myprj.txt");
-        let actual = compute_dst_path(&ctx, &src, &variables).unwrap();
+        let actual = compute_dst_path(&ctx, &src, &LayerScope::default(), &variables).unwrap();
         assert_eq!(&expected, &actual);
     }
 
@@ -607,7 +774,22 @@ mod tests {
 
         let src = ChildPath::new("test/src", "hello/{{ prj }}/sample.txt");
         let expected = ChildPath::new(DST_FOLDER_STR, "hello/myprj/sample.txt");
-        let actual = compute_dst_path(&ctx, &src, &variables).unwrap();
+        let actual = compute_dst_path(&ctx, &src, &LayerScope::default(), &variables).unwrap();
+        assert_eq!(&expected, &actual);
+    }
+
+    #[test]
+    fn test_compute_dst_path_into_dst_subfolder() {
+        let ctx = new_ctx_for_test();
+        let variables = new_variables_for_test();
+
+        let src = ChildPath::new("test/src", "hello/{{ prj }}.txt");
+        let scope = LayerScope::default().child(&cfg::ImportCfg {
+            dst_subfolder: Some("crates/{{ prj }}".to_owned()),
+            ..Default::default()
+        });
+        let expected = ChildPath::new(DST_FOLDER_STR, "crates/myprj/hello/myprj.txt");
+        let actual = compute_dst_path(&ctx, &src, &scope, &variables).unwrap();
         assert_eq!(&expected, &actual);
     }
 
@@ -1,13 +1,19 @@
-use clap::Parser;
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
 use ffizer::ApplyOpts;
+use ffizer::CacheCommand;
+use ffizer::CacheOpts;
 use ffizer::CliOpts;
 use ffizer::Command;
 use ffizer::Ctx;
 use ffizer::ReapplyOpts;
+use ffizer::ShowImportsOpts;
 use ffizer::SourceLoc;
+use ffizer::SourceUri;
 use ffizer::TestSamplesOpts;
+use ffizer::UserCfg;
 use ffizer::provide_json_schema;
 use std::error::Error;
+use std::str::FromStr;
 use tracing::{debug, error, info, trace};
 use tracing_error::ErrorLayer;
 use tracing_subscriber::prelude::*;
@@ -45,15 +51,16 @@ fn init_log(level_min: tracing::Level) {
 }
 
 #[tracing::instrument]
-fn apply(cmd_opt: ApplyOpts) -> Result<(), Box<dyn Error>> {
-    let ctx = Ctx { cmd_opt };
+fn apply(mut cmd_opt: ApplyOpts, user_cfg: UserCfg) -> Result<(), Box<dyn Error>> {
+    cmd_opt.src = cmd_opt.src.resolve_inline()?;
+    let ctx = Ctx { cmd_opt, user_cfg };
     ffizer::process(&ctx)?;
     Ok(())
 }
 
 #[tracing::instrument]
-fn reapply(cmd_opt: ReapplyOpts) -> Result<(), Box<dyn Error>> {
-    ffizer::reprocess(cmd_opt)?;
+fn reapply(cmd_opt: ReapplyOpts, user_cfg: UserCfg) -> Result<(), Box<dyn Error>> {
+    ffizer::reprocess(cmd_opt, user_cfg)?;
     Ok(())
 }
 
@@ -63,6 +70,10 @@ fn inspect() -> Result<(), Box<dyn Error>> {
         "remote cache folder: {}",
         SourceLoc::find_remote_cache_folder()?.to_string_lossy()
     );
+    println!(
+        "user configuration: {}",
+        UserCfg::find_path()?.to_string_lossy()
+    );
     Ok(())
 }
 
@@ -73,30 +84,111 @@ fn show_json_schema() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+#[tracing::instrument]
+fn show_imports(mut cmd_opt: ShowImportsOpts) -> Result<(), Box<dyn Error>> {
+    cmd_opt.src = cmd_opt.src.resolve_inline()?;
+    ffizer::show_imports(cmd_opt)?;
+    Ok(())
+}
+
+#[tracing::instrument]
+fn manage_cache(cmd_opt: CacheOpts) -> Result<(), Box<dyn Error>> {
+    ffizer::manage_cache(cmd_opt)?;
+    Ok(())
+}
+
 #[tracing::instrument]
 fn test_samples(cfg: &TestSamplesOpts) -> Result<(), Box<dyn Error>> {
-    ffizer::tools::test_samples(cfg)?;
+    let cfg = TestSamplesOpts {
+        src: cfg.src.clone().resolve_inline()?,
+        ..cfg.clone()
+    };
+    ffizer::tools::test_samples(&cfg)?;
     Ok(())
 }
 
+/// parse the cli args, with the default values of the options from the user configuration,
+/// and with the aliases and the prefixes of the user expanded into the uri of the source
+/// (an invalid user configuration is only a warning for the help and the subcommands without source)
+fn parse_cli_opts() -> Result<(CliOpts, UserCfg), Box<dyn Error>> {
+    let loaded = UserCfg::load().and_then(|user_cfg| {
+        let cmd = user_cfg.with_default_options(CliOpts::command())?;
+        Ok((user_cfg, cmd))
+    });
+    let (user_cfg, cmd) = match loaded {
+        Ok(v) => v,
+        Err(e) if !requires_user_cfg() => {
+            eprintln!("warning: the user configuration is ignored: {}", e);
+            (UserCfg::default(), CliOpts::command())
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let matches = cmd.get_matches();
+    let mut cli_opts = CliOpts::from_arg_matches(&matches)?;
+    if let Some(raw) = find_raw_uri(&matches) {
+        let uri = SourceUri::from_str(&user_cfg.expand_source(&raw))?;
+        match &mut cli_opts.cmd {
+            Command::Apply(g) => g.src.uri = uri,
+            Command::TestSamples(g) => g.src.uri = uri,
+            Command::ShowImports(g) => g.src.uri = uri,
+            Command::Cache(CacheOpts {
+                cmd: CacheCommand::Clear(g),
+            }) => g.uri = Some(uri),
+            _ => {}
+        }
+    }
+    Ok((cli_opts, user_cfg))
+}
+
+/// is the user configuration used by the subcommand (false for `inspect`, the help,...)
+fn requires_user_cfg() -> bool {
+    let Ok(matches) = CliOpts::command().ignore_errors(true).try_get_matches() else {
+        return false;
+    };
+    match matches.subcommand() {
+        Some(("apply" | "reapply" | "test-samples" | "show-imports", _)) => true,
+        Some(("cache", sub)) => sub.subcommand_name() == Some("clear"),
+        _ => false,
+    }
+}
+
+/// the uri (as typed) of the source (or of the cache entry) of the subcommand
+fn find_raw_uri(matches: &ArgMatches) -> Option<String> {
+    match matches.subcommand() {
+        Some((_, sub)) => find_raw_uri(sub),
+        None => matches
+            .try_get_raw("uri")
+            .ok()
+            .flatten()
+            .and_then(|mut values| values.next())
+            .map(|v| v.to_string_lossy().to_string()),
+    }
+}
+
 fn main() {
     human_panic::setup_panic!();
-    let cli_opts = CliOpts::parse();
+    let (cli_opts, user_cfg) = parse_cli_opts().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    });
 
     let log_level = tracing_level_from_usize(1 + cli_opts.verbose);
     init_log(log_level);
     debug!(cmd = ?&cli_opts, "parsed args");
 
     let r = match &cli_opts.cmd {
-        Command::Apply(g) => apply(g.clone()),
+        Command::Apply(g) => apply(g.clone(), user_cfg),
         Command::Inspect => inspect(),
         Command::ShowJsonSchema => show_json_schema(),
         Command::TestSamples(g) => test_samples(g),
-        Command::Reapply(g) => reapply(g.clone()),
+        Command::Reapply(g) => reapply(g.clone(), user_cfg),
+        Command::ShowImports(g) => show_imports(g.clone()),
+        Command::Cache(g) => manage_cache(g.clone()),
     };
     if let Err(e) = r {
         error!("cmd: {:#?}", &cli_opts);
         error!("failed: {:#?}", &e);
+        error!("{}", &e);
         std::process::exit(1)
     }
 }
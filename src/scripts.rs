@@ -23,6 +23,32 @@ impl Script {
         }
         Ok(())
     }
+
+    /// run the command and return its standard output (trimmed)
+    pub(crate) fn capture(&self) -> Result<Option<String>> {
+        match &self.cmd {
+            Some(cmd) => {
+                let mut options = ScriptOptions::new();
+                options.input_redirection = IoOptions::Inherit;
+                options.output_redirection = IoOptions::Pipe;
+                let args = vec![];
+                let (code, output, error) =
+                    run_script::run(cmd, &args, &options).map_err(|source| Error::ScriptError {
+                        script: cmd.clone(),
+                        source,
+                    })?;
+                if code != 0 {
+                    return Err(Error::ScriptFailed {
+                        script: cmd.clone(),
+                        code,
+                        stderr: error.trim().to_owned(),
+                    });
+                }
+                Ok(Some(output.trim().to_owned()))
+            }
+            None => Ok(None),
+        }
+    }
 }
 
 #[cfg(all(test, not(target_os = "windows")))]
@@ -65,4 +91,28 @@ mod tests {
         let mut stderr_lines = stderr.lines();
         assert_eq!(stderr_lines.next(), Some("🚨 Plaf!"));
     }
+
+    #[test]
+    fn should_capture_trimmed_stdout() {
+        let script = Script {
+            cmd: Some(r#"echo "  hello " && echo "world"; echo "🚨" 1>&2"#.to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(script.capture().unwrap(), Some("hello \nworld".to_owned()));
+    }
+
+    #[test]
+    fn should_fail_to_capture_on_exit_code() {
+        let script = Script {
+            cmd: Some("echo 'oops' 1>&2 && exit 3".to_owned()),
+            ..Default::default()
+        };
+        match script.capture() {
+            Err(Error::ScriptFailed { code, stderr, .. }) => {
+                assert_eq!(code, 3);
+                assert_eq!(stderr, "oops");
+            }
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
 }
@@ -1,4 +1,5 @@
 use crate::ChildPath;
+use crate::cfg::LayerScope;
 use crate::files;
 use std::cmp::{Ord, Ordering};
 use std::path::PathBuf;
@@ -39,6 +40,7 @@ pub struct SourceFile {
     pub childpath: ChildPath,
     pub layer_order: usize,
     pub metadata: SourceFileMetadata,
+    pub(crate) scope: LayerScope,
 }
 
 impl Ord for SourceFile {
@@ -98,6 +100,7 @@ impl From<(ChildPath, usize)> for SourceFile {
                 childpath,
                 layer_order,
                 metadata: SourceFileMetadata::Dir,
+                scope: LayerScope::default(),
             }
         } else if files::is_ffizer_handlebars(&path) {
             SourceFile {
@@ -106,12 +109,14 @@ impl From<(ChildPath, usize)> for SourceFile {
                 metadata: SourceFileMetadata::RenderableFile {
                     extension: files::FILEEXT_HANDLEBARS,
                 },
+                scope: LayerScope::default(),
             }
         } else {
             SourceFile {
                 childpath,
                 layer_order,
                 metadata: SourceFileMetadata::RawFile,
+                scope: LayerScope::default(),
             }
         }
     }
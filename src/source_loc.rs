@@ -1,22 +1,27 @@
+use crate::FetchOpts;
+use crate::archive;
+use crate::cache;
 use crate::error::*;
 use crate::git;
 use crate::source_uri::SourceUri;
 use clap::Args;
 use std::fmt;
 use std::fs;
-use std::path::PathBuf;
-use tracing::warn;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{debug, warn};
 
 #[derive(
     Args, Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord,
 )]
 #[serde(deny_unknown_fields, default)]
 pub struct SourceLoc {
-    /// uri / path of the template
+    /// uri / path of the template (could include the subfolder and the rev as `uri//subfolder@rev`)
     #[arg(short = 's', long = "source")]
     pub uri: SourceUri,
 
-    /// git revision of the template
+    /// git revision of the template (a branch, a tag or a commit sha)
     #[arg(long = "rev", default_value = "master")]
     pub rev: Option<String>,
 
@@ -26,16 +31,52 @@ pub struct SourceLoc {
 }
 
 impl SourceLoc {
-    pub fn find_remote_cache_folder() -> Result<PathBuf> {
+    /// the location from the uri, the rev and the subfolder,
+    /// the uri could include the subfolder and the rev as `uri//subfolder@rev` (they take precedence)
+    pub fn from_parts(
+        uri: &str,
+        rev: Option<String>,
+        subfolder: Option<PathBuf>,
+    ) -> Result<SourceLoc> {
+        let parsed = SourceUri::from_str(uri)?;
+        match split_inline(&parsed) {
+            Some((base, inline_subfolder, inline_rev)) => Ok(SourceLoc {
+                uri: SourceUri::from_str(&base)?,
+                rev: inline_rev.or(rev),
+                subfolder: inline_subfolder.map(PathBuf::from).or(subfolder),
+            }),
+            None => Ok(SourceLoc {
+                uri: parsed,
+                rev,
+                subfolder,
+            }),
+        }
+    }
+
+    /// move the subfolder and the rev included into the uri (`uri//subfolder@rev`) into their fields
+    pub fn resolve_inline(self) -> Result<SourceLoc> {
+        Self::from_parts(&self.uri.raw, self.rev, self.subfolder)
+    }
+
+    pub fn find_cache_folder() -> Result<PathBuf> {
         let app_name = env!("CARGO_PKG_NAME");
         let project_dirs = directories::ProjectDirs::from("", app_name, app_name)
             .ok_or(crate::Error::ApplicationPathNotFound {})?;
-        let cache_base = project_dirs.cache_dir();
-        Ok(cache_base.join("git"))
+        Ok(project_dirs.cache_dir().to_path_buf())
+    }
+
+    pub fn find_remote_cache_folder() -> Result<PathBuf> {
+        Ok(Self::find_cache_folder()?.join(cache::GIT_FOLDER))
+    }
+
+    /// the template is a local archive (extracted into the cache)
+    pub fn is_archive(&self) -> bool {
+        self.uri.host.is_none() && archive::is_archive(&self.uri.path)
     }
 
     pub fn as_local_path(&self) -> Result<PathBuf> {
         let mut path = match self.uri.host {
+            None if self.is_archive() => self.archive_as_local()?,
             None => self
                 .uri
                 .path
@@ -52,31 +93,131 @@ impl SourceLoc {
         Ok(path)
     }
 
+    /// the folder of the cached revs of a remote uri
+    pub fn find_remote_cache_folder_of(uri: &SourceUri) -> Result<PathBuf> {
+        // only the normal components, so the folder stays into the cache (eg `git@host:/srv/repo`)
+        let path = Path::new(uri.host.as_deref().unwrap_or("no_host"))
+            .components()
+            .chain(uri.path.components())
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .collect::<PathBuf>();
+        Ok(Self::find_remote_cache_folder()?.join(path))
+    }
+
+    /// the folder of the uri into the cache: the extracted archive, or the cached revs of a remote uri
+    pub fn find_cache_folder_of(uri: &SourceUri) -> Result<PathBuf> {
+        if uri.host.is_none() && archive::is_archive(&uri.path) {
+            Self::find_archive_cache_folder_of(uri)
+        } else {
+            Self::find_remote_cache_folder_of(uri)
+        }
+    }
+
+    // the archive_as_local ignore subfolder, the folder is unique per path of archive
+    fn archive_as_local(&self) -> Result<PathBuf> {
+        Self::find_archive_cache_folder_of(&self.uri)
+    }
+
+    fn find_archive_cache_folder_of(uri: &SourceUri) -> Result<PathBuf> {
+        let path = uri
+            .path
+            .canonicalize()
+            .map_err(|source| Error::CanonicalizePath {
+                path: uri.path.clone(),
+                source,
+            })?;
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let digest = md5::compute(path.to_string_lossy().as_bytes());
+        Ok(Self::find_cache_folder()?
+            .join(cache::ARCHIVE_FOLDER)
+            .join(format!("{}-{:x}", name, digest)))
+    }
+
     // the remote_as_local ignore subfolder
     fn remote_as_local(&self) -> Result<PathBuf> {
-        let cache_uri = Self::find_remote_cache_folder()?
-            .join(self.uri.host.as_deref().unwrap_or("no_host"))
-            .join(&self.uri.path)
-            .join(self.rev.as_deref().unwrap_or("_default_"));
+        let cache_uri = Self::find_remote_cache_folder_of(&self.uri)?.join(
+            self.rev
+                .as_deref()
+                .map(rev_as_folder_name)
+                .unwrap_or_else(|| "_default_".to_owned()),
+        );
+        // the folder could be removed (eg interrupted clone), so it should be a folder into the cache
+        let cache_folder = Self::find_remote_cache_folder()?;
+        let is_into_cache = cache_uri.strip_prefix(&cache_folder).is_ok_and(|relative| {
+            relative.components().count() > 1
+                && relative
+                    .components()
+                    .all(|c| matches!(c, Component::Normal(_)))
+        });
+        if !is_into_cache {
+            return Err(Error::InvalidCachePath { path: cache_uri });
+        }
         Ok(cache_uri)
     }
-    pub fn download(&self, offline: bool) -> Result<PathBuf> {
-        if !offline && self.uri.host.is_some() {
+    pub fn download(&self, fetch: &FetchOpts) -> Result<PathBuf> {
+        if let Some(locked_commits) = &fetch.locked_commits
+            && self.uri.host.is_some()
+        {
+            let commit = locked_commits
+                .get(&(self.uri.raw.clone(), self.rev.clone()))
+                .ok_or_else(|| Error::TemplateNotLocked {
+                    loc: self.to_string(),
+                })?;
+            let locked = SourceLoc {
+                rev: Some(commit.clone()),
+                ..self.clone()
+            };
+            let fetch = FetchOpts {
+                locked_commits: None,
+                ..fetch.clone()
+            };
+            return locked.download(&fetch);
+        }
+        if self.is_archive() {
+            archive::extract_if_outdated(&self.uri.path, &self.archive_as_local()?, fetch.refresh)?;
+        }
+        if !fetch.offline && self.uri.host.is_some() {
             let remote_path = self.remote_as_local()?;
-            if let Err(v) = git::retrieve(&remote_path, &self.uri.raw, &self.rev) {
+            let ttl = Duration::from_secs(fetch.cache_ttl);
+            if !fetch.refresh && git::is_recently_fetched(&remote_path, ttl) {
+                debug!(path = ?remote_path, "use the cached template (fetched recently)");
+            } else if let Err(v) = git::retrieve(&remote_path, self.uri.git_url(), &self.rev) {
                 warn!(
                     src = ?self,
                     path = ?remote_path,
                     error = ?v,
                     "failed to download"
                 );
-                if remote_path.exists() {
-                    fs::remove_dir_all(&remote_path).map_err(|source| Error::RemoveFolder {
-                        path: remote_path,
-                        source,
-                    })?;
+                // keep a usable checkout (eg network failure), remove the others (eg interrupted clone)
+                let commit = remote_path
+                    .join(".git")
+                    .join("HEAD")
+                    .exists()
+                    .then(|| git::find_head_commit(&remote_path).ok())
+                    .flatten();
+                match commit {
+                    Some(commit) if !fetch.strict_fetch => {
+                        warn!(
+                            "failed to fetch {}, use the previous cached checkout (commit {}): {}",
+                            self, commit, v
+                        );
+                    }
+                    Some(_) => return Err(v),
+                    None => {
+                        if remote_path.exists() {
+                            fs::remove_dir_all(&remote_path).map_err(|source| {
+                                Error::RemoveFolder {
+                                    path: remote_path,
+                                    source,
+                                }
+                            })?;
+                        }
+                        return Err(v);
+                    }
                 }
-                return Err(v);
             }
         }
         let path = self.as_local_path()?;
@@ -92,21 +233,86 @@ impl SourceLoc {
     }
 }
 
+/// encode the rev as a single folder name (eg branch "feature/foo" into "feature%2Ffoo"),
+/// so every rev of a repository has its own folder into the cache
+fn rev_as_folder_name(rev: &str) -> String {
+    rev.chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || (c == '.' && i > 0) {
+                c.to_string()
+            } else {
+                format!("%{:02X}", c as u32)
+            }
+        })
+        .collect()
+}
+
+/// split the `//subfolder` and the `@rev` at the end of the uri into (uri, subfolder, rev),
+/// only for remote uris and for local archives (the rev is ignored, a local path could contain `@`)
+fn split_inline(uri: &SourceUri) -> Option<(String, Option<String>, Option<String>)> {
+    let path = uri.path.to_string_lossy();
+    let base = uri.raw.strip_suffix(path.as_ref())?;
+    let (path, rev, subfolder) = if uri.host.is_some() {
+        let (path, rev) = split_rev(&path);
+        match path.split_once("//") {
+            Some((path, subfolder)) => (path, rev, Some(subfolder)),
+            None => (path, rev, None),
+        }
+    } else {
+        // a local path could start with `//` (unc path), so only split after an archive
+        let i = path
+            .match_indices("//")
+            .map(|(i, _)| i)
+            .find(|i| archive::is_archive(Path::new(&path[..*i])))?;
+        (&path[..i], None, Some(&path[i + 2..]))
+    };
+    let subfolder = subfolder.filter(|s| !s.is_empty());
+    if rev.is_none() && subfolder.is_none() {
+        return None;
+    }
+    Some((
+        format!("{}{}", base, path),
+        subfolder.map(str::to_owned),
+        rev.map(str::to_owned),
+    ))
+}
+
+/// split the rev after the last `@` of the path (not at the start of a segment like `@scope`),
+/// the rev could contain `/` (eg `feature/foo`)
+fn split_rev(path: &str) -> (&str, Option<&str>) {
+    match path.rfind('@') {
+        Some(i) if i > 0 && !path[..i].ends_with('/') && i + 1 < path.len() => {
+            (&path[..i], Some(&path[i + 1..]))
+        }
+        _ => (path, None),
+    }
+}
+
+impl FromStr for SourceLoc {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_parts(s, None, None)
+    }
+}
+
 impl fmt::Display for SourceLoc {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{} ({}{})",
-            self.uri.raw,
-            self.rev
-                .as_ref()
-                .map(|s| format!("rev: '{}' ", s))
-                .unwrap_or_default(),
+        let details = [
+            self.rev.as_ref().map(|s| format!("rev: '{}'", s)),
             self.subfolder
                 .as_ref()
-                .map(|s| format!("subfolder: '{}'", s.to_string_lossy()))
-                .unwrap_or_default()
-        )
+                .map(|s| format!("subfolder: '{}'", s.to_string_lossy())),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+        if details.is_empty() {
+            write!(f, "{}", self.uri.raw)
+        } else {
+            write!(f, "{} ({})", self.uri.raw, details.join(", "))
+        }
     }
 }
 // #[cfg(test)]
@@ -127,3 +333,132 @@ impl fmt::Display for SourceLoc {
 //         Ok(())
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("master", "master")]
+    #[case("v1.2.0", "v1.2.0")]
+    #[case("feature/foo", "feature%2Ffoo")]
+    #[case("..", "%2E.")]
+    #[case("3f2a9c1", "3f2a9c1")]
+    fn test_rev_as_folder_name(#[case] rev: &str, #[case] expected: &str) {
+        assert_eq!(rev_as_folder_name(rev), expected);
+    }
+
+    #[rstest]
+    #[case::absolute_path("host", "/srv/git/tmpl", "host/srv/git/tmpl/master")]
+    #[case::parent_in_path("host", "/../../tmpl", "host/tmpl/master")]
+    #[case::parent_as_host("..", "../tmpl", "tmpl/master")]
+    fn test_remote_as_local_should_stay_into_cache(
+        #[case] host: &str,
+        #[case] path: &str,
+        #[case] expected: &str,
+    ) {
+        let src = SourceLoc {
+            uri: SourceUri {
+                raw: format!("git@{}:{}", host, path),
+                host: Some(host.to_owned()),
+                path: PathBuf::from(path),
+                ..SourceUri::default()
+            },
+            rev: Some("master".to_owned()),
+            subfolder: None,
+        };
+        assert_eq!(
+            src.remote_as_local().unwrap(),
+            SourceLoc::find_remote_cache_folder()
+                .unwrap()
+                .join(expected)
+        );
+    }
+
+    #[test]
+    fn download_should_fail_when_remote_template_is_not_locked() {
+        let src = SourceLoc {
+            uri: SourceUri::from_str("git@github.com:ffizer/ffizer.git").unwrap(),
+            rev: Some("master".to_owned()),
+            subfolder: None,
+        };
+        let fetch = FetchOpts {
+            locked_commits: Some(Default::default()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            src.download(&fetch),
+            Err(Error::TemplateNotLocked { .. })
+        ));
+    }
+
+    #[rstest]
+    #[case::remote("gh:ffizer/templates", "git@github.com:ffizer/templates", None, None)]
+    #[case::remote_with_subfolder_and_rev(
+        "gh:ffizer/templates//rust/lib@v2",
+        "git@github.com:ffizer/templates",
+        Some("rust/lib"),
+        Some("v2")
+    )]
+    #[case::remote_with_rev(
+        "https://github.com/ffizer/templates.git@feature/foo",
+        "https://github.com/ffizer/templates.git",
+        None,
+        Some("feature/foo")
+    )]
+    #[case::remote_with_subfolder(
+        "https://github.com/ffizer/templates.git//rust",
+        "https://github.com/ffizer/templates",
+        Some("rust"),
+        None
+    )]
+    #[case::remote_with_at_into_path(
+        "https://example.com/@scope/templates//rust@v2",
+        "https://example.com/@scope/templates",
+        Some("rust"),
+        Some("v2")
+    )]
+    #[case::local_with_at("./tmpl@1.0", "./tmpl@1.0", None, None)]
+    #[case::local_with_double_slash("tmpl//child", "tmpl//child", None, None)]
+    #[case::archive_with_subfolder(
+        "file:///tmp/tmpl-1.0.tar.gz//tmpl-1.0",
+        "file:///tmp/tmpl-1.0.tar.gz",
+        Some("tmpl-1.0"),
+        None
+    )]
+    #[case::archive_path_with_subfolder("tmpl.zip//child@v2", "tmpl.zip", Some("child@v2"), None)]
+    fn test_source_loc_from_str(
+        #[case] input: &str,
+        #[case] uri: &str,
+        #[case] subfolder: Option<&str>,
+        #[case] rev: Option<&str>,
+    ) {
+        let actual = SourceLoc::from_str(input).unwrap();
+        assert_eq!(actual.uri, SourceUri::from_str(uri).unwrap());
+        assert_eq!(actual.subfolder, subfolder.map(PathBuf::from));
+        assert_eq!(actual.rev.as_deref(), rev);
+    }
+
+    #[test]
+    fn test_source_loc_from_parts_should_prefer_inline() {
+        let actual = SourceLoc::from_parts(
+            "gh:ffizer/templates//rust@v2",
+            Some("master".to_owned()),
+            Some(PathBuf::from("go")),
+        )
+        .unwrap();
+        assert_eq!(actual.rev.as_deref(), Some("v2"));
+        assert_eq!(actual.subfolder, Some(PathBuf::from("rust")));
+
+        let actual = SourceLoc::from_parts(
+            "gh:ffizer/templates",
+            Some("master".to_owned()),
+            Some(PathBuf::from("go")),
+        )
+        .unwrap();
+        assert_eq!(actual.rev.as_deref(), Some("master"));
+        assert_eq!(actual.subfolder, Some(PathBuf::from("go")));
+    }
+}
@@ -1,5 +1,4 @@
 use crate::error::*;
-use regex::Regex;
 use serde_plain::derive_deserialize_from_fromstr;
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -10,64 +9,195 @@ use std::str::FromStr;
 // - uri_parser = "0.2.0" use explicit lifetime for URI, too hard for intergration with CmdOpt
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
 pub struct SourceUri {
+    /// the uri as provided (abbreviation expanded, without `.git` suffix for remote), used for display
     pub raw: String,
-    pub path: PathBuf,
+    /// scheme of the url (eg "https", "ssh", "git+https"), none for scp-like syntax and local path
+    pub scheme: Option<String>,
+    pub user: Option<String>,
+    /// none for local path
     pub host: Option<String>,
+    pub port: Option<u16>,
+    pub path: PathBuf,
 }
 
 derive_deserialize_from_fromstr!(SourceUri, "source uri");
 
+const REMOTE_SCHEMES: [&str; 7] = [
+    "http",
+    "https",
+    "ssh",
+    "git",
+    "git+http",
+    "git+https",
+    "git+ssh",
+];
+
 impl FromStr for SourceUri {
     type Err = crate::Error;
 
     fn from_str(s: &str) -> Result<Self> {
-        let url_re = Regex::new(
-            r"^(https?|ssh)://([[:alnum:]:\._-]+@)?(?P<host>[[:alnum:]\._-]+)(:\d+)?/(?P<path>[[:alnum:]\._\-/]+)$",
-        ).map_err(|source| Error::ParseGitUri{value: s.to_owned(), source})?;
-        // let url_re2 = Regex::new(
-        //     r"^(https?|ssh)://([[:alnum:]:\._-]+@)?(?P<host>[[:alnum:]\._-]+)(:\d+)?/(?P<path>[[:alnum:]\._\-/]+)$",
-        // ).map_err(|source| Error::ParseGitUri{value: s.to_owned(), source})?;
-        let git_re = Regex::new(r"^git@(?P<host>[[:alnum:]\._-]+):(?P<path>[[:alnum:]\._\-/]+)$")
-            .map_err(|source| Error::ParseGitUri {
+        let text = if let Some(rest) = s.strip_prefix("gh:") {
+            format!("git@github.com:{}", rest)
+        } else if let Some(rest) = s.strip_prefix("gl:") {
+            format!("git@gitlab.com:{}", rest)
+        } else if let Some(rest) = s.strip_prefix("bb:") {
+            format!("git@bitbucket.org:{}", rest)
+        } else {
+            s.to_owned()
+        };
+        let invalid = |reason: &str| Error::ParseSourceUri {
             value: s.to_owned(),
-            source,
-        })?;
-        // let git_re2 = Regex::new(r"^git@(?P<host>[[:alnum:]\._-]+):(?P<path>[[:alnum:]\._\-/]+)$")
-        //     .map_err(|source| Error::ParseGitUri {
-        //         value: s.to_owned(),
-        //         source,
-        //     })?;
-
-        let mut text = s.strip_suffix(".git").unwrap_or(s).to_owned();
-        if s.starts_with("gh:") {
-            text = s.replacen("gh:", "git@github.com:", 1);
-        } else if s.starts_with("gl:") {
-            text = s.replacen("gl:", "git@gitlab.com:", 1);
-        } else if s.starts_with("bb:") {
-            text = s.replacen("bb:", "git@bitbucket.org:", 1);
+            reason: reason.to_owned(),
+        };
+
+        if let Some((scheme, rest)) = split_scheme(&text) {
+            if scheme == "file" {
+                return Ok(SourceUri::local(&text, &file_url_path(rest)));
+            }
+            if !REMOTE_SCHEMES.contains(&scheme.as_str()) {
+                return Err(invalid("unsupported scheme"));
+            }
+            let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+            let (user, host_port) = match authority.rsplit_once('@') {
+                Some((user, host_port)) => (Some(user.to_owned()), host_port),
+                None => (None, authority),
+            };
+            let (host, port) = split_port(host_port).ok_or_else(|| invalid("invalid port"))?;
+            if !is_host(host) {
+                return Err(invalid("invalid host"));
+            }
+            let path = strip_git_suffix(path);
+            if path.is_empty() {
+                return Err(invalid("missing path"));
+            }
+            return Ok(SourceUri {
+                raw: strip_git_suffix(&text).to_owned(),
+                scheme: Some(scheme),
+                user,
+                host: Some(host.to_owned()),
+                port,
+                path: PathBuf::from(path),
+            });
         }
 
-        git_re
-            .captures(&text)
-            // .or_else(|| git_re2.captures(&text))
-            .or_else(|| url_re.captures(&text))
-            // .or_else(|| url_re2.captures(&text))
-            .map(|caps| SourceUri {
-                raw: text.clone(),
-                path: PathBuf::from(caps["path"].to_owned()),
-                host: Some(caps["host"].to_owned()),
-            })
-            .or_else(|| {
-                Some(SourceUri {
-                    raw: text,
-                    path: PathBuf::from(change_local_path_sep(s)),
-                    host: None,
-                })
-            })
-            .ok_or_else(|| Error::Unknown("failed to parse source uri".to_owned()))
+        // scp-like syntax `[user@]host:path` (like git: not a local path if there is no slash before the colon)
+        if let Some((user_host, path)) = text.split_once(':') {
+            let (user, host) = match user_host.split_once('@') {
+                Some((user, host)) => (Some(user.to_owned()), host),
+                None => (None, user_host),
+            };
+            let is_drive = host.len() == 1 && user.is_none();
+            let path = strip_git_suffix(path);
+            if !is_drive && is_host(host) && !path.is_empty() {
+                return Ok(SourceUri {
+                    raw: strip_git_suffix(&text).to_owned(),
+                    scheme: None,
+                    user,
+                    host: Some(host.to_owned()),
+                    port: None,
+                    path: PathBuf::from(path),
+                });
+            }
+        }
+
+        Ok(SourceUri::local(&text, &text))
     }
 }
 
+impl SourceUri {
+    fn local(raw: &str, path: &str) -> SourceUri {
+        SourceUri {
+            raw: raw.to_owned(),
+            scheme: None,
+            user: None,
+            host: None,
+            port: None,
+            path: PathBuf::from(change_local_path_sep(path)),
+        }
+    }
+
+    /// the url to use with git (without the `git+` prefix of the scheme)
+    pub fn git_url(&self) -> String {
+        let Some(host) = &self.host else {
+            return self.path.to_string_lossy().to_string();
+        };
+        let user = self
+            .user
+            .as_ref()
+            .map(|u| format!("{}@", u))
+            .unwrap_or_default();
+        let host = if host.contains(':') {
+            format!("[{}]", host)
+        } else {
+            host.clone()
+        };
+        let path = self.path.to_string_lossy();
+        match &self.scheme {
+            Some(scheme) => {
+                let scheme = scheme.strip_prefix("git+").unwrap_or(scheme);
+                let port = self.port.map(|p| format!(":{}", p)).unwrap_or_default();
+                format!("{}://{}{}{}/{}", scheme, user, host, port, path)
+            }
+            None => format!("{}{}:{}", user, host, path),
+        }
+    }
+}
+
+/// split `scheme://rest` (the scheme is lowercased), a single letter is a windows drive not a scheme
+fn split_scheme(s: &str) -> Option<(String, &str)> {
+    let (scheme, rest) = s.split_once("://")?;
+    let mut chars = scheme.chars();
+    let is_scheme = scheme.len() > 1
+        && chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+    is_scheme.then(|| (scheme.to_ascii_lowercase(), rest))
+}
+
+/// the local path of the rest of a `file://` url (`file:///path`, `file://localhost/path` or `file://server/share` as UNC)
+fn file_url_path(rest: &str) -> String {
+    let path = match rest.strip_prefix("localhost/") {
+        Some(path) => format!("/{}", path),
+        None if rest.starts_with('/') => rest.to_owned(),
+        None => format!("//{}", rest),
+    };
+    // `file:///C:/foo` is `C:/foo`
+    let bytes = path.as_bytes();
+    if bytes.len() > 2 && bytes[0] == b'/' && bytes[1].is_ascii_alphabetic() && bytes[2] == b':' {
+        path[1..].to_owned()
+    } else {
+        path
+    }
+}
+
+/// split `host[:port]` (`[ipv6][:port]`), none if the port is invalid
+fn split_port(host_port: &str) -> Option<(&str, Option<u16>)> {
+    let (host, port) = match host_port.strip_prefix('[') {
+        Some(rest) => {
+            let (host, after) = rest.split_once(']')?;
+            (host, after.strip_prefix(':'))
+        }
+        None => match host_port.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (host_port, None),
+        },
+    };
+    match port {
+        Some(port) => Some((host, Some(port.parse().ok()?))),
+        None => Some((host, None)),
+    }
+}
+
+fn is_host(host: &str) -> bool {
+    !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ".-_:".contains(c))
+}
+
+fn strip_git_suffix(s: &str) -> &str {
+    s.strip_suffix(".git").unwrap_or(s)
+}
+
 //HACK to support Path -> string -> Path
 fn change_local_path_sep(s: &str) -> String {
     if cfg!(windows) {
@@ -82,11 +212,7 @@ fn change_local_path_sep(s: &str) -> String {
 
 impl Default for SourceUri {
     fn default() -> Self {
-        SourceUri {
-            raw: ".".to_owned(),
-            path: PathBuf::from("."),
-            host: None,
-        }
+        SourceUri::local(".", ".")
     }
 }
 
@@ -96,69 +222,328 @@ mod tests {
     use rstest::*;
     use similar_asserts::assert_eq;
 
+    fn local(raw: &str, path: &str) -> SourceUri {
+        SourceUri::local(raw, path)
+    }
+
+    fn remote(
+        raw: &str,
+        scheme: Option<&str>,
+        user: Option<&str>,
+        host: &str,
+        port: Option<u16>,
+        path: &str,
+    ) -> SourceUri {
+        SourceUri {
+            raw: raw.to_owned(),
+            scheme: scheme.map(str::to_owned),
+            user: user.map(str::to_owned),
+            host: Some(host.to_owned()),
+            port,
+            path: PathBuf::from(path),
+        }
+    }
+
     #[rstest]
-    #[case::abs_localpath("/foo/bar", "/foo/bar", "/foo/bar", None)]
+    #[case::abs_localpath("/foo/bar", local("/foo/bar", "/foo/bar"), "/foo/bar")]
+    #[case::rel_localpath("foo/bar", local("foo/bar", "foo/bar"), "foo/bar")]
+    #[case::localpath_with_spaces(
+        "my templates/tmpl 1",
+        local("my templates/tmpl 1", "my templates/tmpl 1"),
+        "my templates/tmpl 1"
+    )]
+    #[case::localpath_with_at("./tmpl@1.0", local("./tmpl@1.0", "./tmpl@1.0"), "./tmpl@1.0")]
+    #[case::localpath_with_colon_after_slash("/tmp/a:b", local("/tmp/a:b", "/tmp/a:b"), "/tmp/a:b")]
+    #[case::localpath_with_git_extension(
+        "/tmp/tmpl.git",
+        local("/tmp/tmpl.git", "/tmp/tmpl.git"),
+        "/tmp/tmpl.git"
+    )]
+    #[case::windows_drive(
+        "C:/templates/tmpl",
+        local("C:/templates/tmpl", "C:/templates/tmpl"),
+        "C:/templates/tmpl"
+    )]
+    #[case::windows_unc(
+        r"\\server\share\tmpl",
+        local(r"\\server\share\tmpl", r"\\server\share\tmpl"),
+        &change_local_path_sep(r"\\server\share\tmpl")
+    )]
+    #[case::file_url(
+        "file:///foo/bar.tar.gz",
+        local("file:///foo/bar.tar.gz", "/foo/bar.tar.gz"),
+        "/foo/bar.tar.gz"
+    )]
+    #[case::file_url_localhost(
+        "file://localhost/foo/bar.tgz",
+        local("file://localhost/foo/bar.tgz", "/foo/bar.tgz"),
+        "/foo/bar.tgz"
+    )]
+    #[case::file_url_unc(
+        "file://server/share/tmpl.zip",
+        local("file://server/share/tmpl.zip", "//server/share/tmpl.zip"),
+        &change_local_path_sep("//server/share/tmpl.zip")
+    )]
+    #[case::file_url_windows_drive(
+        "file:///C:/foo/tmpl.zip",
+        local("file:///C:/foo/tmpl.zip", "C:/foo/tmpl.zip"),
+        "C:/foo/tmpl.zip"
+    )]
     #[case::git_with_git_extension(
         "git@github.com:ffizer/ffizer.git",
-        "git@github.com:ffizer/ffizer",
-        "ffizer/ffizer",
-        Some("github.com")
+        remote(
+            "git@github.com:ffizer/ffizer",
+            None,
+            Some("git"),
+            "github.com",
+            None,
+            "ffizer/ffizer"
+        ),
+        "git@github.com:ffizer/ffizer"
     )]
     #[case::git_without_git_extension(
         "git@github.com:ffizer/ffizer",
-        "git@github.com:ffizer/ffizer",
-        "ffizer/ffizer",
-        Some("github.com")
+        remote(
+            "git@github.com:ffizer/ffizer",
+            None,
+            Some("git"),
+            "github.com",
+            None,
+            "ffizer/ffizer"
+        ),
+        "git@github.com:ffizer/ffizer"
+    )]
+    #[case::scp_without_user(
+        "github.com:ffizer/ffizer",
+        remote(
+            "github.com:ffizer/ffizer",
+            None,
+            None,
+            "github.com",
+            None,
+            "ffizer/ffizer"
+        ),
+        "github.com:ffizer/ffizer"
+    )]
+    #[case::scp_with_absolute_path(
+        "git@example.com:/srv/git/tmpl.git",
+        remote(
+            "git@example.com:/srv/git/tmpl",
+            None,
+            Some("git"),
+            "example.com",
+            None,
+            "/srv/git/tmpl"
+        ),
+        "git@example.com:/srv/git/tmpl"
     )]
     #[case::https_with_git_extension(
         "https://github.com/ffizer/ffizer.git",
-        "https://github.com/ffizer/ffizer",
-        "ffizer/ffizer",
-        Some("github.com")
+        remote(
+            "https://github.com/ffizer/ffizer",
+            Some("https"),
+            None,
+            "github.com",
+            None,
+            "ffizer/ffizer"
+        ),
+        "https://github.com/ffizer/ffizer"
     )]
     #[case::https_without_git_extension(
         "https://github.com/ffizer/ffizer",
-        "https://github.com/ffizer/ffizer",
-        "ffizer/ffizer",
-        Some("github.com")
+        remote(
+            "https://github.com/ffizer/ffizer",
+            Some("https"),
+            None,
+            "github.com",
+            None,
+            "ffizer/ffizer"
+        ),
+        "https://github.com/ffizer/ffizer"
     )]
     #[case::https_with_git_extension_and_username(
         "https://user@github.com/ffizer/ffizer.git",
-        "https://user@github.com/ffizer/ffizer",
-        "ffizer/ffizer",
-        Some("github.com")
+        remote(
+            "https://user@github.com/ffizer/ffizer",
+            Some("https"),
+            Some("user"),
+            "github.com",
+            None,
+            "ffizer/ffizer"
+        ),
+        "https://user@github.com/ffizer/ffizer"
+    )]
+    #[case::https_with_port(
+        "https://git.example.com:8443/team/tmpl.git",
+        remote(
+            "https://git.example.com:8443/team/tmpl",
+            Some("https"),
+            None,
+            "git.example.com",
+            Some(8443),
+            "team/tmpl"
+        ),
+        "https://git.example.com:8443/team/tmpl"
+    )]
+    #[case::https_with_at_into_path(
+        "https://example.com/@scope/tmpl",
+        remote(
+            "https://example.com/@scope/tmpl",
+            Some("https"),
+            None,
+            "example.com",
+            None,
+            "@scope/tmpl"
+        ),
+        "https://example.com/@scope/tmpl"
+    )]
+    #[case::http(
+        "http://example.com/tmpl",
+        remote(
+            "http://example.com/tmpl",
+            Some("http"),
+            None,
+            "example.com",
+            None,
+            "tmpl"
+        ),
+        "http://example.com/tmpl"
+    )]
+    #[case::ssh_with_user_and_port(
+        "ssh://git@git.example.com:2222/team/tmpl.git",
+        remote(
+            "ssh://git@git.example.com:2222/team/tmpl",
+            Some("ssh"),
+            Some("git"),
+            "git.example.com",
+            Some(2222),
+            "team/tmpl"
+        ),
+        "ssh://git@git.example.com:2222/team/tmpl"
+    )]
+    #[case::ssh_without_user(
+        "ssh://git.example.com/team/tmpl",
+        remote(
+            "ssh://git.example.com/team/tmpl",
+            Some("ssh"),
+            None,
+            "git.example.com",
+            None,
+            "team/tmpl"
+        ),
+        "ssh://git.example.com/team/tmpl"
+    )]
+    #[case::ssh_with_ipv6(
+        "ssh://git@[::1]:2222/tmpl",
+        remote(
+            "ssh://git@[::1]:2222/tmpl",
+            Some("ssh"),
+            Some("git"),
+            "::1",
+            Some(2222),
+            "tmpl"
+        ),
+        "ssh://git@[::1]:2222/tmpl"
+    )]
+    #[case::git_protocol(
+        "git://example.com/tmpl",
+        remote(
+            "git://example.com/tmpl",
+            Some("git"),
+            None,
+            "example.com",
+            None,
+            "tmpl"
+        ),
+        "git://example.com/tmpl"
+    )]
+    #[case::git_plus_https(
+        "git+https://github.com/ffizer/ffizer.git",
+        remote(
+            "git+https://github.com/ffizer/ffizer",
+            Some("git+https"),
+            None,
+            "github.com",
+            None,
+            "ffizer/ffizer"
+        ),
+        "https://github.com/ffizer/ffizer"
+    )]
+    #[case::uppercase_scheme(
+        "HTTPS://github.com/ffizer/ffizer",
+        remote(
+            "HTTPS://github.com/ffizer/ffizer",
+            Some("https"),
+            None,
+            "github.com",
+            None,
+            "ffizer/ffizer"
+        ),
+        "https://github.com/ffizer/ffizer"
     )]
     #[case::abbreviation_gh(
         "gh:ffizer/ffizer",
-        "git@github.com:ffizer/ffizer",
-        "ffizer/ffizer",
-        Some("github.com")
+        remote(
+            "git@github.com:ffizer/ffizer",
+            None,
+            Some("git"),
+            "github.com",
+            None,
+            "ffizer/ffizer"
+        ),
+        "git@github.com:ffizer/ffizer"
     )]
     #[case::abbreviation_gl(
         "gl:ffizer/ffizer",
-        "git@gitlab.com:ffizer/ffizer",
-        "ffizer/ffizer",
-        Some("gitlab.com")
+        remote(
+            "git@gitlab.com:ffizer/ffizer",
+            None,
+            Some("git"),
+            "gitlab.com",
+            None,
+            "ffizer/ffizer"
+        ),
+        "git@gitlab.com:ffizer/ffizer"
     )]
     #[case::abbreviation_bitbucket(
         "bb:ffizer/ffizer",
-        "git@bitbucket.org:ffizer/ffizer",
-        "ffizer/ffizer",
-        Some("bitbucket.org")
+        remote(
+            "git@bitbucket.org:ffizer/ffizer",
+            None,
+            Some("git"),
+            "bitbucket.org",
+            None,
+            "ffizer/ffizer"
+        ),
+        "git@bitbucket.org:ffizer/ffizer"
     )]
     fn assert_source_uri_from_str(
         #[case] input: &str,
-        #[case] raw: &str,
-        #[case] path: &str,
-        #[case] host: Option<&str>,
+        #[case] expected: SourceUri,
+        #[case] git_url: &str,
     ) {
+        let actual = SourceUri::from_str(input).unwrap();
+        assert_eq!(&actual, &expected);
+        assert_eq!(actual.git_url(), git_url);
+        // round-trip: the url for git is parsed into the same location
+        let parsed = SourceUri::from_str(&actual.git_url()).unwrap();
         assert_eq!(
-            &SourceUri::from_str(input).unwrap(),
-            &SourceUri {
-                raw: raw.to_owned(),
-                path: PathBuf::from(path.to_owned()),
-                host: host.map(|s| s.into()),
-            }
+            (&parsed.user, &parsed.host, &parsed.port, &parsed.path),
+            (&actual.user, &actual.host, &actual.port, &actual.path)
         );
     }
+
+    #[rstest]
+    #[case::unsupported_scheme("ftp://example.com/tmpl", "unsupported scheme")]
+    #[case::missing_host("https:///ffizer/ffizer", "invalid host")]
+    #[case::invalid_host("https://exa mple.com/tmpl", "invalid host")]
+    #[case::port_out_of_range("https://example.com:99999/tmpl", "invalid port")]
+    #[case::port_not_a_number("ssh://git@example.com:port/tmpl", "invalid port")]
+    #[case::missing_path("https://example.com", "missing path")]
+    fn assert_source_uri_from_str_failed(#[case] input: &str, #[case] reason: &str) {
+        match SourceUri::from_str(input) {
+            Err(Error::ParseSourceUri { reason: actual, .. }) => assert_eq!(actual, reason),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
 }
@@ -2,15 +2,19 @@ use self::persist::*;
 use crate::cfg::{ImportCfg, TemplateCfg};
 use crate::variables::Variables;
 use crate::{Result, SourceLoc, SourceUri};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::path::Path;
 use std::str::FromStr;
 
 mod persist;
 
+pub(crate) use self::persist::LockedTemplate;
+
 pub(crate) const FFIZER_DATASTORE_DIRNAME: &str = ".ffizer";
 const OPTIONS_FILENAME: &str = "options.yaml";
 const VERSION_FILENAME: &str = "version.txt";
+const LOCK_FILENAME: &str = "lock.yaml";
 
 pub(crate) fn make_template(options: PersistedOptions) -> TemplateCfg {
     // not ready for standalone command, only used as part of reapply for now
@@ -22,6 +26,7 @@ pub(crate) fn make_template(options: PersistedOptions) -> TemplateCfg {
             uri: source.uri,
             rev: source.rev,
             subfolder: source.subfolder.map(|x| x.to_string_lossy().to_string()),
+            ..Default::default()
         })
         .collect();
 
@@ -80,12 +85,14 @@ pub(crate) fn load_options(folder: &Path) -> Result<PersistedOptions> {
 pub(crate) fn make_new_options(
     previous_opts: PersistedOptions,
     variables: &Variables,
+    namespaces: &BTreeSet<String>,
     source: &SourceLoc,
 ) -> Result<PersistedOptions> {
     let variables_to_save: Vec<PersistedVariable> = {
         let mut vars: Variables = previous_opts.variables.try_into()?;
-        vars.append(&mut variables.clone());
+        vars.merge_nested(&mut variables.clone(), namespaces);
         vars.retain(|k, _v| !k.starts_with("ffizer_"));
+        vars.remove_secrets();
         vars.into()
     };
 
@@ -123,12 +130,13 @@ pub(crate) fn make_new_options(
 
 pub(crate) fn save_options(
     variables: &Variables,
+    namespaces: &BTreeSet<String>,
     source: &SourceLoc,
     dst_folder: &Path,
 ) -> Result<()> {
     let previous_options = load_options(dst_folder)?;
 
-    let options = make_new_options(previous_options, variables, source)?;
+    let options = make_new_options(previous_options, variables, namespaces, source)?;
 
     let ffizer_folder = dst_folder.join(FFIZER_DATASTORE_DIRNAME);
     if !ffizer_folder.exists() {
@@ -147,6 +155,75 @@ pub(crate) fn save_options(
     Ok(())
 }
 
+pub(crate) fn load_lock(folder: &Path) -> Result<PersistedLock> {
+    let lock_path = folder.join(FFIZER_DATASTORE_DIRNAME).join(LOCK_FILENAME);
+    if lock_path.exists() {
+        Ok(serde_yaml::from_reader(std::fs::File::open(lock_path)?)?)
+    } else {
+        Ok(PersistedLock::default())
+    }
+}
+
+/// the locked commits, by (uri, rev) of the templates
+pub(crate) fn get_locked_commits(
+    folder: &Path,
+) -> Result<BTreeMap<(String, Option<String>), String>> {
+    Ok(load_lock(folder)?
+        .templates
+        .into_iter()
+        .map(|t| (t.key(), t.commit))
+        .collect())
+}
+
+/// the lock with the new commits (the templates not used keep their previous commit),
+/// and the templates locked previously at an other commit
+pub(crate) fn make_new_lock(
+    previous_lock: PersistedLock,
+    commits: &[(&SourceLoc, &str)],
+) -> (PersistedLock, Vec<(LockedTemplate, String)>) {
+    let mut templates: Vec<LockedTemplate> = vec![];
+    for (loc, commit) in commits {
+        let template = LockedTemplate {
+            uri: loc.uri.raw.clone(),
+            rev: loc.rev.clone(),
+            commit: commit.to_string(),
+        };
+        if !templates.iter().any(|t| t.key() == template.key()) {
+            templates.push(template);
+        }
+    }
+    let mut changes = vec![];
+    for previous in previous_lock.templates {
+        match templates.iter().find(|t| t.key() == previous.key()) {
+            Some(t) if t.commit != previous.commit => changes.push((t.clone(), previous.commit)),
+            Some(_) => {}
+            None => templates.push(previous),
+        }
+    }
+    (PersistedLock { templates }, changes)
+}
+
+/// save the commits of the remote templates into the lock of `dst_folder`,
+/// returns the templates locked previously at an other commit (with the previous commit)
+pub(crate) fn save_lock(
+    commits: &[(&SourceLoc, &str)],
+    dst_folder: &Path,
+) -> Result<Vec<(LockedTemplate, String)>> {
+    let previous_lock = load_lock(dst_folder)?;
+    let (lock, changes) = make_new_lock(previous_lock, commits);
+    if !lock.templates.is_empty() {
+        let ffizer_folder = dst_folder.join(FFIZER_DATASTORE_DIRNAME);
+        if !ffizer_folder.exists() {
+            std::fs::create_dir(&ffizer_folder)?;
+        }
+        serde_yaml::to_writer(
+            std::fs::File::create(ffizer_folder.join(LOCK_FILENAME))?,
+            &lock,
+        )?;
+    }
+    Ok(changes)
+}
+
 #[allow(dead_code)] // Used in testing
 pub(crate) fn get_saved_sources(folder: &Path) -> Result<Vec<SourceLoc>> {
     load_options(folder)?
@@ -193,6 +270,7 @@ mod tests {
                 dst_folder: dst.to_path_buf(),
                 ..Default::default()
             },
+            ..Default::default()
         }
     }
 
@@ -207,6 +285,26 @@ mod tests {
 
         save_options(
             &variables_with_ffizer,
+            &BTreeSet::new(),
+            &ctx.cmd_opt.src,
+            &ctx.cmd_opt.dst_folder,
+        )
+        .unwrap();
+        let saved_variables = get_saved_variables(&ctx.cmd_opt.dst_folder).unwrap();
+        assert_eq!(saved_variables, variables);
+    }
+
+    #[rstest]
+    fn test_save_load_variables_without_secret(tmp_dir: TempDir, variables: Variables) {
+        let ctx = new_ctx_from(tmp_dir.path());
+
+        let mut variables_with_secret = variables.clone();
+        variables_with_secret.insert("token", "s3cr3t").unwrap();
+        variables_with_secret.mark_secret("token");
+
+        save_options(
+            &variables_with_secret,
+            &BTreeSet::new(),
             &ctx.cmd_opt.src,
             &ctx.cmd_opt.dst_folder,
         )
@@ -245,7 +343,13 @@ mod tests {
         ) {
             let ctx_1 = new_ctx_from_src_dst(&source_1, tmp_dir.path());
 
-            save_options(&variables, &ctx_1.cmd_opt.src, &ctx_1.cmd_opt.dst_folder).unwrap();
+            save_options(
+                &variables,
+                &BTreeSet::new(),
+                &ctx_1.cmd_opt.src,
+                &ctx_1.cmd_opt.dst_folder,
+            )
+            .unwrap();
 
             let saved_sources = get_saved_sources(&ctx_1.cmd_opt.dst_folder).unwrap();
 
@@ -263,8 +367,20 @@ mod tests {
             let ctx_1 = new_ctx_from_src_dst(&source_1, tmp_dir.path());
             let ctx_2 = new_ctx_from_src_dst(&source_2, tmp_dir.path());
 
-            save_options(&variables, &ctx_1.cmd_opt.src, &ctx_1.cmd_opt.dst_folder).unwrap();
-            save_options(&variables, &ctx_2.cmd_opt.src, &ctx_2.cmd_opt.dst_folder).unwrap();
+            save_options(
+                &variables,
+                &BTreeSet::new(),
+                &ctx_1.cmd_opt.src,
+                &ctx_1.cmd_opt.dst_folder,
+            )
+            .unwrap();
+            save_options(
+                &variables,
+                &BTreeSet::new(),
+                &ctx_2.cmd_opt.src,
+                &ctx_2.cmd_opt.dst_folder,
+            )
+            .unwrap();
 
             let saved_sources = get_saved_sources(&ctx_1.cmd_opt.dst_folder).unwrap();
 
@@ -286,8 +402,20 @@ mod tests {
             let ctx_1 = new_ctx_from_src_dst(&source_1, tmp_dir.path());
             let ctx_2 = new_ctx_from_src_dst(&source_2, tmp_dir.path());
 
-            save_options(&variables, &ctx_1.cmd_opt.src, &ctx_1.cmd_opt.dst_folder).unwrap();
-            save_options(&variables, &ctx_2.cmd_opt.src, &ctx_2.cmd_opt.dst_folder).unwrap();
+            save_options(
+                &variables,
+                &BTreeSet::new(),
+                &ctx_1.cmd_opt.src,
+                &ctx_1.cmd_opt.dst_folder,
+            )
+            .unwrap();
+            save_options(
+                &variables,
+                &BTreeSet::new(),
+                &ctx_2.cmd_opt.src,
+                &ctx_2.cmd_opt.dst_folder,
+            )
+            .unwrap();
 
             let saved_sources = get_saved_sources(&ctx_1.cmd_opt.dst_folder).unwrap();
 
@@ -331,6 +459,7 @@ mod tests {
                     uri: "path/to/foo".to_string(),
                     rev: None,
                     subfolder: None,
+                    ..Default::default()
                 }],
                 use_template_dir: true,
                 ..Default::default()
@@ -362,11 +491,13 @@ mod tests {
                         uri: "path/to/foo".to_string(),
                         rev: None,
                         subfolder: None,
+                        ..Default::default()
                     },
                     ImportCfg {
                         uri: "http://blabla.truc/a/path".into(),
                         rev: Some("master".into()),
                         subfolder: Some("some_subfolder".into()),
+                        ..Default::default()
                     },
                 ],
                 use_template_dir: true,
@@ -375,4 +506,90 @@ mod tests {
             assert_eq!(expected, make_template(options))
         }
     }
+    mod test_lock {
+        use super::*;
+        use similar_asserts::assert_eq;
+
+        fn locked(uri: &str, rev: Option<&str>, commit: &str) -> LockedTemplate {
+            LockedTemplate {
+                uri: uri.to_owned(),
+                rev: rev.map(str::to_owned),
+                commit: commit.to_owned(),
+            }
+        }
+
+        fn remote_source(rev: Option<&str>) -> SourceLoc {
+            SourceLoc {
+                uri: SourceUri::from_str("http://blabla.truc/a/path").unwrap(),
+                rev: rev.map(str::to_owned),
+                subfolder: None,
+            }
+        }
+
+        #[rstest]
+        fn save_load(tmp_dir: TempDir) {
+            let master = remote_source(Some("master"));
+            let other = SourceLoc {
+                subfolder: Some(PathBuf::from("other")),
+                ..master.clone()
+            };
+            let changes = save_lock(&[(&master, "aaa"), (&other, "aaa")], tmp_dir.path()).unwrap();
+            assert_eq!(changes, vec![]);
+
+            let expected = PersistedLock {
+                templates: vec![locked("http://blabla.truc/a/path", Some("master"), "aaa")],
+            };
+            assert_eq!(load_lock(tmp_dir.path()).unwrap(), expected);
+        }
+
+        #[rstest]
+        fn nothing_to_lock(tmp_dir: TempDir) {
+            save_lock(&[], tmp_dir.path()).unwrap();
+            assert!(!tmp_dir.path().join(FFIZER_DATASTORE_DIRNAME).exists());
+        }
+
+        #[rstest]
+        fn update_and_keep_unused() {
+            let previous = PersistedLock {
+                templates: vec![
+                    locked("http://blabla.truc/a/path", Some("master"), "aaa"),
+                    locked("http://blabla.truc/a/path", Some("v1"), "bbb"),
+                    locked("http://blabla.truc/a/path", None, "ccc"),
+                ],
+            };
+            let master = remote_source(Some("master"));
+            let default = remote_source(None);
+            let (lock, changes) = make_new_lock(previous, &[(&master, "ddd"), (&default, "ccc")]);
+
+            let expected = PersistedLock {
+                templates: vec![
+                    locked("http://blabla.truc/a/path", Some("master"), "ddd"),
+                    locked("http://blabla.truc/a/path", None, "ccc"),
+                    locked("http://blabla.truc/a/path", Some("v1"), "bbb"),
+                ],
+            };
+            assert_eq!(lock, expected);
+            assert_eq!(
+                changes,
+                vec![(
+                    locked("http://blabla.truc/a/path", Some("master"), "ddd"),
+                    "aaa".to_owned()
+                )]
+            );
+        }
+
+        #[rstest]
+        fn locked_commits(tmp_dir: TempDir) {
+            let master = remote_source(Some("master"));
+            save_lock(&[(&master, "aaa")], tmp_dir.path()).unwrap();
+            let commits = get_locked_commits(tmp_dir.path()).unwrap();
+            assert_eq!(
+                commits.get(&(
+                    "http://blabla.truc/a/path".to_owned(),
+                    Some("master".to_owned())
+                )),
+                Some(&"aaa".to_owned())
+            );
+        }
+    }
 }
@@ -1,8 +1,6 @@
 use std::path::PathBuf;
-use std::str::FromStr;
 
 use crate::SourceLoc;
-use crate::SourceUri;
 use crate::error::*;
 use crate::variables::Variables;
 
@@ -19,6 +17,25 @@ pub struct PersistedSrc {
     pub subfolder: Option<PathBuf>,
 }
 
+/// the commits checked out for the remote templates
+#[derive(Debug, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct PersistedLock {
+    pub templates: Vec<LockedTemplate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LockedTemplate {
+    pub uri: String,
+    pub rev: Option<String>,
+    pub commit: String,
+}
+
+impl LockedTemplate {
+    pub fn key(&self) -> (String, Option<String>) {
+        (self.uri.clone(), self.rev.clone())
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PersistedVariable {
     pub name: String,
@@ -37,11 +54,7 @@ impl From<SourceLoc> for PersistedSrc {
 
 impl TryFrom<PersistedSrc> for SourceLoc {
     fn try_from(value: PersistedSrc) -> Result<Self> {
-        Ok(SourceLoc {
-            uri: SourceUri::from_str(&value.uri)?,
-            rev: value.rev,
-            subfolder: value.subfolder,
-        })
+        SourceLoc::from_parts(&value.uri, value.rev, value.subfolder)
     }
     type Error = crate::Error;
 }
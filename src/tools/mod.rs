@@ -12,7 +12,7 @@ use tempfile::{TempDir, tempdir};
 use tracing::info;
 
 pub fn test_samples(cfg: &TestSamplesOpts) -> Result<()> {
-    let template_base_path = &cfg.src.download(cfg.offline)?;
+    let template_base_path = &cfg.src.download(&cfg.fetch)?;
     if !check_samples(template_base_path, &cfg.src, cfg.review)? {
         Err(crate::Error::TestSamplesFailed {})
     } else {
@@ -305,6 +305,7 @@ impl SampleRun {
         }
         let ctx = crate::Ctx {
             cmd_opt: sample.args.clone(),
+            ..Default::default()
         };
         crate::process(&ctx)?;
         let diffs = dir_diff_list::search_diff(destination, &sample.expected, &sample.ignores)?;
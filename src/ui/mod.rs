@@ -1,20 +1,31 @@
 mod tree;
 
 use crate::FileOperation;
+use crate::cache::CacheEntry;
+use crate::cfg::ImportNode;
 use crate::cfg::TransformsValues;
 use crate::cfg::VariableCfg;
+use crate::cfg::VariableValueCfg;
 use crate::cli_opt::*;
 use crate::error::*;
+use crate::scripts::Script;
+use crate::timeline::LockedTemplate;
 use crate::variable_def::LabelValue;
 use crate::variable_def::VariableDef;
+use crate::variables::MASKED_VALUE;
 use crate::{Action, Ctx, Variables};
 use cliclack::confirm;
 use cliclack::input;
 use cliclack::note;
+use cliclack::password;
 use cliclack::select;
 use console::Style;
 use handlebars_misc_helpers::new_hbs;
 use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
 use tracing::{Level, debug, instrument, span, warn};
 
 #[derive(Debug)]
@@ -28,6 +39,8 @@ pub struct VariableRequest {
     prompt: String,
     default_value: Option<VariableResponse>,
     values: Vec<String>,
+    secret: bool,
+    required: bool,
 }
 
 #[instrument]
@@ -57,8 +70,12 @@ fn to_variabledef(v: &VariableCfg) -> Result<VariableDef> {
         name: v.name.clone(),
         default_value: v.default_value.as_ref().map(|v| v.0.clone()),
         ask: v.ask.clone(),
+        help: v.help.clone(),
+        group: v.group.clone(),
         hidden,
         select_in_values,
+        secret: v.is_secret(),
+        required: v.required.unwrap_or(v.default_value.is_none()),
     })
 }
 
@@ -70,24 +87,36 @@ pub(crate) fn outro(message: &str) -> Result<()> {
     cliclack::outro(message).map_err(Error::from)
 }
 
+/// ask the values of the variables not yet in `init`, and tell if at least one question was asked
 pub(crate) fn ask_variables(
     ctx: &Ctx,
     list_variables: &[VariableCfg],
     mut init: Variables,
-) -> Result<Variables> {
+) -> Result<(Variables, bool)> {
     let mut variables = Variables::default();
+    let mut asked = false;
     variables.append(&mut init);
     let handlebars = new_hbs();
 
-    intro("Configure variables")?;
+    let mut missing = vec![];
+    let mut current_group: Option<String> = None;
     // TODO optimize to reduce clones
     for variable_cfg in list_variables.iter().cloned() {
         let _span_ = span!(Level::DEBUG, "ask_variables", ?variable_cfg).entered();
-        if variables.contains_key(&variable_cfg.name) {
+        let key = variable_cfg.key();
+        if variable_cfg.is_secret() {
+            variables.mark_secret(&key);
+        }
+        if let Some(value) = variables.get(&key).cloned() {
+            // the value could be provided with a flat key (eg `-v backend.name=foo`)
+            variable_cfg
+                .scope
+                .insert(&mut variables, &variable_cfg.name, value)?;
             continue;
         }
+        let view = variable_cfg.scope.view(&variables)?;
         let render = |v: &str| {
-            let r = handlebars.render_template(v, &variables);
+            let r = handlebars.render_template(v, &view);
             match r {
                 Ok(s) => s,
                 Err(e) => {
@@ -96,20 +125,35 @@ pub(crate) fn ask_variables(
                 }
             }
         };
-        let variable_cfg = variable_cfg.transforms_values(&render)?;
+        let mut variable_cfg = variable_cfg.transforms_values(&render)?;
+        if let Some(cmd) = &variable_cfg.default_from_cmd
+            && let Some(value) = default_from_cmd(ctx, &key, cmd)?
+        {
+            variable_cfg.default_value = Some(VariableValueCfg(value));
+        }
         let variable = to_variabledef(&variable_cfg)?;
-        let name = variable.name;
+        let is_asked = !(variable.hidden || ctx.cmd_opt.no_interaction);
+        if is_asked && variable.group != current_group {
+            if let Some(group) = &variable.group {
+                note(group, "")?;
+            }
+            current_group = variable.group.clone();
+        }
         let request = {
             let prompt = if let Some(ask) = variable.ask {
                 handlebars
-                    .render_template(&ask, &variables)
+                    .render_template(&ask, &view)
                     .map_err(|source| Error::Handlebars {
-                        when: format!("define prompt for '{}'", &name),
+                        when: format!("define prompt for '{}'", &key),
                         template: Box::new(ask.clone()),
                         source: Box::new(source),
                     })?
             } else {
-                name.clone()
+                key.clone()
+            };
+            let prompt = match variable.help.as_ref().filter(|h| !h.is_empty()) {
+                Some(help) => format!("{}\n{}", prompt, Style::new().dim().apply_to(help)),
+                None => prompt,
             };
             let values: Vec<String> = variable
                 .select_in_values
@@ -142,21 +186,34 @@ pub(crate) fn ask_variables(
                 prompt,
                 default_value,
                 values,
+                secret: variable.secret,
+                required: variable.required,
             }
         };
-        let resp = if variable.hidden || ctx.cmd_opt.no_interaction {
-            request.default_value.unwrap_or(VariableResponse {
-                value: "".to_owned(),
-                idx: None,
-            })
+        let resp = if !is_asked {
+            match request.default_value {
+                Some(default_value) => default_value,
+                None if variable.required && !variable.hidden => {
+                    missing.push(key);
+                    continue;
+                }
+                None => VariableResponse {
+                    value: "".to_owned(),
+                    idx: None,
+                },
+            }
         } else {
+            asked = true;
             ask_variable_value(request)?
         };
+        let scope = &variable_cfg.scope;
+        let name = &variable_cfg.name;
         if let Some(idx) = resp.idx {
-            variables.insert(format!("{}__idx", name), idx)?;
-            variables.insert(format!("{}__label", name), resp.value)?;
-            variables.insert(
-                name.clone(),
+            scope.insert(&mut variables, &format!("{}__idx", name), idx)?;
+            scope.insert(&mut variables, &format!("{}__label", name), resp.value)?;
+            scope.insert(
+                &mut variables,
+                name,
                 variable
                     .select_in_values
                     .get(idx)
@@ -165,21 +222,351 @@ pub(crate) fn ask_variables(
                     .clone(),
             )?;
         } else {
-            variables.insert(name.clone(), Variables::value_from_str(&resp.value)?)?;
+            scope.insert(
+                &mut variables,
+                name,
+                Variables::value_from_str(&resp.value)?,
+            )?;
+        }
+    }
+    if !missing.is_empty() {
+        return Err(Error::MissingRequiredVariables { names: missing });
+    }
+    Ok((variables, asked))
+}
+
+/// compute a default value from the output of the command (run only after confirmation)
+fn default_from_cmd(ctx: &Ctx, name: &str, cmd: &str) -> Result<Option<serde_yaml::Value>> {
+    if ctx.cmd_opt.fetch.offline || ctx.cmd_opt.no_interaction {
+        debug!(name, cmd, "skip command to compute default value");
+        return Ok(None);
+    }
+    let template_name = format!("default value of '{}'", name);
+    if !confirm_run_script(ctx, template_name, cmd, false)? {
+        return Ok(None);
+    }
+    let script = Script {
+        cmd: Some(cmd.to_owned()),
+        ..Default::default()
+    };
+    match script.capture() {
+        Ok(Some(value)) if !value.is_empty() => Ok(Some(serde_yaml::Value::String(value))),
+        Ok(_) => Ok(None),
+        Err(err) => {
+            warn!(?err, name, "failed to compute default value");
+            Ok(None)
+        }
+    }
+}
+
+/// show a summary of the answers, and return the name of the variable to answer again (if any)
+pub(crate) fn review_variables(
+    ctx: &Ctx,
+    list_variables: &[VariableCfg],
+    variables: &Variables,
+) -> Result<Option<String>> {
+    if ctx.cmd_opt.no_interaction {
+        return Ok(None);
+    }
+    let handlebars = new_hbs();
+    let mut names = vec![];
+    let mut summary = String::new();
+    for variable_cfg in list_variables {
+        let key = variable_cfg.key();
+        let view = variable_cfg.scope.view(variables)?;
+        let render = |v: &str| handlebars.render_template(v, &view).unwrap_or(v.into());
+        let variable = to_variabledef(&variable_cfg.transforms_values(&render)?)?;
+        let Some(value) = variables.get(&key) else {
+            continue;
+        };
+        if variable.hidden {
+            continue;
+        }
+        let raw_value = Variables::value_as_str(value)?;
+        let value = variables.mask_value(&key, &raw_value);
+        let label_key = format!("{}__label", key);
+        let line = match variables.get(&label_key) {
+            Some(serde_yaml::Value::String(label)) if label.trim() != raw_value => {
+                format!(
+                    "{} ({})",
+                    variables.mask_value(&label_key, label.trim()),
+                    value
+                )
+            }
+            _ => value,
+        };
+        summary.push_str(&format!("   {}: {}\n", key, line));
+        names.push(key);
+    }
+    if names.is_empty() {
+        return Ok(None);
+    }
+    note("Answers", summary)?;
+    let mut items = vec![(None, "confirm answers".to_owned(), "")];
+    items.extend(
+        names
+            .into_iter()
+            .map(|name| (Some(name.clone()), format!("change {}", name), "")),
+    );
+    let selected = select("Are the answers correct ?")
+        .items(&items)
+        .interact()?;
+    Ok(selected)
+}
+
+/// the name of the preset to use: the one from cli, else the one selected by the user (if any),
+/// the user is not asked if the destination has saved answers (eg on reapply), they are kept
+pub(crate) fn select_preset(
+    ctx: &Ctx,
+    presets: &BTreeMap<String, Variables>,
+    saved: &Variables,
+) -> Result<Option<String>> {
+    if let Some(preset) = &ctx.cmd_opt.preset {
+        if !presets.contains_key(preset) {
+            return Err(Error::StringValueNotIn {
+                value_name: "preset".to_owned(),
+                value: preset.clone(),
+                accepted: presets.keys().cloned().collect(),
+            });
         }
+        return Ok(Some(preset.clone()));
+    }
+    if presets.is_empty() || ctx.cmd_opt.no_interaction || !saved.tree().is_empty() {
+        return Ok(None);
     }
-    Ok(variables)
+    let mut items = vec![(None, "none".to_owned(), "")];
+    items.extend(
+        presets
+            .keys()
+            .map(|name| (Some(name.clone()), name.clone(), "")),
+    );
+    let selected = select("preset").items(&items).interact()?;
+    Ok(selected)
+}
+
+/// print the tree of imports, with the order of the layers
+pub fn show_imports(nodes: &[ImportNode]) -> Result<()> {
+    let prefixes = tree::provide_prefix(nodes, |parent, item| parent.depth + 1 == item.depth);
+    for (node, prefix) in nodes.iter().zip(prefixes.iter()) {
+        let repeated = if node.repeated {
+            " (already imported)"
+        } else {
+            ""
+        };
+        println!("{}[{}] {}{}", prefix, node.order, node.loc, repeated);
+    }
+    Ok(())
+}
+
+/// show the remote templates checked out at an other commit than the one of the lock
+pub(crate) fn show_updated_templates(changes: &[(LockedTemplate, String)]) -> Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let mut msg = String::new();
+    for (template, previous_commit) in changes {
+        msg.push_str(&format!(
+            "   - {}{}: {} -> {}\n",
+            template.uri,
+            template
+                .rev
+                .as_ref()
+                .map(|rev| format!(" (rev: '{}')", rev))
+                .unwrap_or_default(),
+            short_sha(previous_commit),
+            short_sha(&template.commit),
+        ));
+    }
+    note("Updated templates", msg).map_err(Error::from)
+}
+
+fn short_sha(commit: &str) -> &str {
+    commit.get(..7).unwrap_or(commit)
+}
+
+/// print the cached templates
+pub(crate) fn show_cache_entries(cache_folder: &Path, entries: &[CacheEntry]) -> Result<()> {
+    println!("cache folder: {}", cache_folder.to_string_lossy());
+    let width = entries
+        .iter()
+        .map(|e| e.relative.to_string_lossy().len())
+        .max()
+        .unwrap_or_default();
+    for entry in entries {
+        let action = if entry.archive {
+            "extracted"
+        } else {
+            "fetched"
+        };
+        let fetched = entry
+            .fetched_at
+            .and_then(|t| SystemTime::now().duration_since(t).ok())
+            .map(|age| format!("{} {} ago", action, format_duration(age)))
+            .unwrap_or_else(|| format!("never {}", action));
+        let commit = if entry.is_corrupted() {
+            "corrupted"
+        } else if entry.archive {
+            "archive"
+        } else {
+            entry.commit.as_deref().map(short_sha).unwrap_or_default()
+        };
+        println!(
+            "{:width$}  {:>9}  {:<18}  {}",
+            entry.relative.to_string_lossy(),
+            format_size(entry.size),
+            fetched,
+            commit,
+        );
+    }
+    Ok(())
+}
+
+pub(crate) fn show_removed_cache_entries(entries: &[CacheEntry]) -> Result<()> {
+    for entry in entries {
+        let corrupted = if entry.is_corrupted() {
+            " (corrupted)"
+        } else {
+            ""
+        };
+        println!("removed {}{}", entry.relative.to_string_lossy(), corrupted);
+    }
+    let size: u64 = entries.iter().map(|e| e.size).sum();
+    println!(
+        "{} cached template(s) removed, {} freed",
+        entries.len(),
+        format_size(size)
+    );
+    Ok(())
+}
+
+fn format_size(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, units[0])
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+/// print the variables' definitions (without asking values)
+pub fn show_variables(
+    list_variables: &[VariableCfg],
+    presets: &BTreeMap<String, Variables>,
+) -> Result<()> {
+    if !presets.is_empty() {
+        println!("presets:");
+        for (name, values) in presets {
+            let names = values.tree().keys().cloned().collect::<Vec<_>>();
+            println!("- {} ({})", name, names.join(", "));
+        }
+        println!();
+    }
+    let mut current_group: Option<&String> = None;
+    for variable in list_variables {
+        if variable.group.as_ref() != current_group {
+            current_group = variable.group.as_ref();
+            match current_group {
+                Some(group) => println!("\n[{}]", group),
+                None => println!(),
+            }
+        }
+        let mut flags = vec![];
+        if variable
+            .required
+            .unwrap_or(variable.default_value.is_none())
+        {
+            flags.push("required");
+        }
+        if variable.is_secret() {
+            flags.push("secret");
+        }
+        // a templated condition is only known when applying, so it's displayed as is
+        let hidden = variable
+            .hidden
+            .as_deref()
+            .map(|v| serde_yaml::from_str::<bool>(v).map_err(|_| v));
+        if let Some(Ok(true)) = hidden {
+            flags.push("hidden");
+        }
+        if flags.is_empty() {
+            println!("- {}", variable.key());
+        } else {
+            println!("- {} ({})", variable.key(), flags.join(", "));
+        }
+        if let Some(Err(condition)) = hidden {
+            println!("    hidden: {}", condition);
+        }
+        if let Some(ask) = &variable.ask {
+            println!("    ask: {}", ask);
+        }
+        if let Some(help) = &variable.help {
+            println!("    help: {}", help);
+        }
+        if let Some(env) = &variable.env {
+            println!("    env: {}", env);
+        }
+        if let Some(default_from_cmd) = &variable.default_from_cmd {
+            println!("    default_from_cmd: {}", default_from_cmd);
+        }
+        if let Some(default_value) = &variable.default_value {
+            let default_value = if variable.is_secret() {
+                MASKED_VALUE.to_owned()
+            } else {
+                Variables::value_as_str(&default_value.0)?
+            };
+            println!("    default: {}", default_value);
+        }
+        if let Some(select_in_values) = &variable.select_in_values {
+            let values: Vec<LabelValue> = select_in_values.into();
+            let labels = values.iter().map(|v| v.label.trim()).collect::<Vec<_>>();
+            println!("    values: {}", labels.join(", "));
+        }
+    }
+    Ok(())
 }
 
 pub fn ask_variable_value(req: VariableRequest) -> Result<VariableResponse> {
-    if req.values.is_empty() {
+    if req.secret && req.values.is_empty() {
+        // never display the default value of a secret, keep it when input is empty
+        let value = match req.default_value {
+            Some(default_value) => {
+                let value = password(format!("{} (empty to keep default)", &req.prompt))
+                    .allow_empty()
+                    .interact()?;
+                if value.is_empty() {
+                    default_value.value
+                } else {
+                    value
+                }
+            }
+            None if req.required => password(&req.prompt).interact()?,
+            None => password(&req.prompt).allow_empty().interact()?,
+        };
+        Ok(VariableResponse { value, idx: None })
+    } else if req.values.is_empty() {
         let value = match req.default_value {
             Some(v) if v.value == "true" || v.value == "false" => confirm(&req.prompt)
                 .initial_value(v.value == "true")
                 .interact()
                 .map(|r| r.to_string())?,
             _ => {
-                let mut input = input(&req.prompt);
+                let mut input = input(&req.prompt).required(req.required);
                 if let Some(default_value) = req.default_value {
                     input = input.default_input(&default_value.value);
                 }
@@ -219,14 +606,31 @@ fn format_operation(op: &FileOperation) -> Cow<'static, str> {
 }
 
 //TODO add flag to filter display: all, changes, none
-pub fn confirm_plan(ctx: &Ctx, actions: &[Action]) -> Result<bool> {
-    debug!(?actions, "plan");
+pub fn confirm_plan(ctx: &Ctx, actions: &[Action], variables: &Variables) -> Result<bool> {
+    debug!(actions = %variables.mask_secrets(&format!("{:?}", actions)), "plan");
+    // the parent folder of an item could be missing from the plan
+    // (eg the folders of a `dst_subfolder` or of a `rename` of imports), so attach it to its closest ancestor
     let prefixes = tree::provide_prefix(actions, |parent, item| {
-        Some(parent.dst_path.relative.as_path()) == item.dst_path.relative.parent()
+        item.dst_path
+            .relative
+            .starts_with(&parent.dst_path.relative)
+            && item.dst_path.relative != parent.dst_path.relative
     });
+    let relatives = actions
+        .iter()
+        .map(|a| a.dst_path.relative.as_path())
+        .collect::<HashSet<_>>();
     let mut plan = String::new();
     for (a, prefix) in actions.iter().zip(prefixes.iter()) {
-        let p = a.dst_path.base.join(&a.dst_path.relative);
+        let relative = &a.dst_path.relative;
+        let full_path = a.dst_path.base.join(relative);
+        let name = match relative.ancestors().skip(1).find(|p| relatives.contains(p)) {
+            Some(ancestor) => relative
+                .strip_prefix(ancestor)
+                .ok()
+                .and_then(|v| v.to_str()),
+            None => full_path.file_name().and_then(|v| v.to_str()),
+        };
         plan.push_str(&format!(
             "   - {} \x1B[38;2;{};{};{}m{}\x1B[0m{}\n",
             format_operation(&a.operation),
@@ -234,7 +638,7 @@ pub fn confirm_plan(ctx: &Ctx, actions: &[Action]) -> Result<bool> {
             80,
             80,
             prefix,
-            p.file_name().and_then(|v| v.to_str()).unwrap_or("???"),
+            variables.mask_secrets(name.unwrap_or("???")),
         ));
     }
     note("Plan to execute", plan)?;
@@ -400,3 +804,45 @@ pub fn confirm_run_script(
             .map_err(Error::from)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_select_preset_should_not_ask_over_saved_answers() {
+        let ctx = Ctx::default();
+        assert!(!ctx.cmd_opt.no_interaction);
+        let presets = BTreeMap::from([("service".to_owned(), Variables::default())]);
+        let mut saved = Variables::default();
+        saved.insert("kind", "lib").unwrap();
+        assert_eq!(select_preset(&ctx, &presets, &saved).unwrap(), None);
+    }
+
+    #[test]
+    fn test_ask_variables_should_not_ask_provided_values() {
+        let ctx = Ctx::default();
+        assert!(!ctx.cmd_opt.no_interaction);
+        let list = vec![
+            VariableCfg {
+                name: "kind".to_owned(),
+                ..Default::default()
+            },
+            VariableCfg {
+                name: "name".to_owned(),
+                default_value: Some(VariableValueCfg("{{ kind }}-x".into())),
+                hidden: Some("true".to_owned()),
+                ..Default::default()
+            },
+        ];
+        let mut init = Variables::default();
+        init.insert("kind", "lib").unwrap();
+        let (variables, asked) = ask_variables(&ctx, &list, init).unwrap();
+        assert_eq!(asked, false);
+        assert_eq!(
+            variables.get("name"),
+            Some(&serde_yaml::Value::String("lib-x".to_owned()))
+        );
+    }
+}
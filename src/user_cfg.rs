@@ -0,0 +1,231 @@
+use crate::error::*;
+use crate::variables::Variables;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// environment variable with the path of the user configuration (in place of `<config folder>/config.yaml`)
+pub const USER_CFG_ENV: &str = "FFIZER_CONFIG";
+const USER_CFG_FILENAME: &str = "config.yaml";
+
+/// configuration of the user (shared by every call of ffizer)
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct UserCfg {
+    /// prefixes of uri (eg `corp: "git@git.corp.example.com:"` to use `corp:team/template`),
+    /// they override the builtin `gh`, `gl` and `bb`
+    pub prefixes: BTreeMap<String, String>,
+    /// named templates (eg `rust-lib: "gh:ffizer/templates//rust/lib@v2"` to use `--source rust-lib`)
+    pub aliases: BTreeMap<String, String>,
+    /// default values of the cli options, by long name (eg `update-mode: keep`)
+    pub defaults: BTreeMap<String, serde_yaml::Value>,
+    /// default values of variables (eg `author: "Jane Doe"`), suggested when the variable is asked
+    pub variables: BTreeMap<String, serde_yaml::Value>,
+}
+
+impl UserCfg {
+    /// path of the user configuration (`FFIZER_CONFIG` or `config.yaml` into the config folder)
+    pub fn find_path() -> Result<PathBuf> {
+        if let Some(path) = std::env::var_os(USER_CFG_ENV) {
+            return Ok(PathBuf::from(path));
+        }
+        let app_name = env!("CARGO_PKG_NAME");
+        let project_dirs = directories::ProjectDirs::from("", app_name, app_name)
+            .ok_or(crate::Error::ApplicationPathNotFound {})?;
+        Ok(project_dirs.config_dir().join(USER_CFG_FILENAME))
+    }
+
+    /// load the user configuration (empty if the file doesn't exist)
+    pub fn load() -> Result<UserCfg> {
+        let path = Self::find_path()?;
+        if !path.exists() {
+            return Ok(UserCfg::default());
+        }
+        let content = fs::read_to_string(&path).map_err(|source| Error::ReadFile {
+            path: path.clone(),
+            source,
+        })?;
+        serde_yaml::from_str(&content).map_err(|source| Error::ReadUserCfg { path, source })
+    }
+
+    /// the uri of a source with the alias and the prefix of the user expanded
+    /// (the builtin prefixes are expanded when the uri is parsed),
+    /// an existing local path is kept as is (even if an alias has the same name)
+    pub fn expand_source(&self, uri: &str) -> String {
+        if Path::new(uri).exists() {
+            return uri.to_owned();
+        }
+        let uri = self.find_alias(uri).unwrap_or(uri);
+        self.expand_prefix(uri).unwrap_or_else(|| uri.to_owned())
+    }
+
+    /// the uri with its prefix expanded (eg `corp:team/tmpl` into `git@git.corp.example.com:team/tmpl`)
+    pub(crate) fn expand_prefix(&self, uri: &str) -> Option<String> {
+        let (prefix, rest) = uri.split_once(':')?;
+        if rest.starts_with("//") {
+            return None;
+        }
+        let expansion = self.prefixes.get(prefix)?;
+        Some(format!("{}{}", expansion, rest))
+    }
+
+    /// the source named `name` (eg `gh:ffizer/templates//rust/lib@v2` for `rust-lib`)
+    pub(crate) fn find_alias(&self, name: &str) -> Option<&str> {
+        self.aliases.get(name).map(String::as_str)
+    }
+
+    pub(crate) fn find_variables(&self) -> Result<Variables> {
+        let mut variables = Variables::default();
+        for (name, value) in &self.variables {
+            variables.insert(name, value)?;
+        }
+        Ok(variables)
+    }
+
+    /// the cli command with the default values of the options of the user
+    pub fn with_default_options(&self, cmd: clap::Command) -> Result<clap::Command> {
+        let defaults = self
+            .defaults
+            .iter()
+            .map(|(name, value)| (name.as_str(), value_as_string(value)))
+            .collect::<BTreeMap<_, _>>();
+        let mut unused = defaults.keys().copied().collect::<BTreeSet<_>>();
+        let cmd = set_default_options(cmd, &defaults, &mut unused);
+        match unused.first() {
+            Some(name) => Err(Error::UnknownUserCfgOption {
+                name: (*name).to_owned(),
+            }),
+            None => Ok(cmd),
+        }
+    }
+}
+
+fn value_as_string(value: &serde_yaml::Value) -> String {
+    match value {
+        serde_yaml::Value::String(s) => s.clone(),
+        serde_yaml::Value::Null => String::new(),
+        v => serde_yaml::to_string(v)
+            .map(|s| s.trim_end().to_owned())
+            .unwrap_or_default(),
+    }
+}
+
+/// set the default values of the options (by long name) of the command and of its subcommands
+fn set_default_options<'a>(
+    mut cmd: clap::Command,
+    defaults: &BTreeMap<&'a str, String>,
+    unused: &mut BTreeSet<&'a str>,
+) -> clap::Command {
+    for (name, value) in defaults {
+        let id = cmd
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(name))
+            .map(|arg| arg.get_id().clone());
+        if let Some(id) = id {
+            cmd = cmd.mut_arg(id, |arg| arg.default_value(value.clone()));
+            unused.remove(name);
+        }
+    }
+    let names = cmd
+        .get_subcommands()
+        .map(|sub| sub.get_name().to_owned())
+        .collect::<Vec<_>>();
+    for name in names {
+        cmd = cmd.mut_subcommand(name, |sub| set_default_options(sub, defaults, unused));
+    }
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CliOpts;
+    use clap::{CommandFactory, FromArgMatches};
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn sample() -> UserCfg {
+        serde_yaml::from_str(
+            r#"
+            prefixes:
+              corp: "git@git.corp.example.com:"
+              gh: "https://github.com/"
+            aliases:
+              rust-lib: "gh:ffizer/templates//rust/lib@v2"
+            defaults:
+              update-mode: keep
+              no-interaction: true
+              cache-ttl: 600
+            variables:
+              author: "Jane Doe"
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[rstest]
+    #[case("corp:team/tmpl", Some("git@git.corp.example.com:team/tmpl"))]
+    #[case("gh:ffizer/ffizer", Some("https://github.com/ffizer/ffizer"))]
+    #[case("gl:ffizer/ffizer", None)]
+    #[case("https://github.com/ffizer/ffizer", None)]
+    #[case("git@github.com:ffizer/ffizer", None)]
+    #[case("foo:bar", None)]
+    fn test_expand_prefix(#[case] uri: &str, #[case] expected: Option<&str>) {
+        assert_eq!(sample().expand_prefix(uri).as_deref(), expected);
+    }
+
+    #[test]
+    fn test_find_alias() {
+        let cfg = sample();
+        assert_eq!(
+            cfg.find_alias("rust-lib"),
+            Some("gh:ffizer/templates//rust/lib@v2")
+        );
+        assert_eq!(cfg.find_alias("rust"), None);
+    }
+
+    #[rstest]
+    #[case::alias("rust-lib", "https://github.com/ffizer/templates//rust/lib@v2")]
+    #[case::prefix("corp:team/tmpl", "git@git.corp.example.com:team/tmpl")]
+    #[case::builtin_prefix("gl:ffizer/ffizer", "gl:ffizer/ffizer")]
+    #[case::other("tests/data/template_1", "tests/data/template_1")]
+    fn test_expand_source(#[case] uri: &str, #[case] expected: &str) {
+        assert_eq!(sample().expand_source(uri), expected);
+    }
+
+    #[test]
+    fn test_expand_source_should_keep_existing_local_path() {
+        let cfg = UserCfg {
+            aliases: BTreeMap::from([("src".to_owned(), "gh:ffizer/ffizer".to_owned())]),
+            ..Default::default()
+        };
+        assert_eq!(cfg.expand_source("src"), "src");
+        assert_eq!(cfg.expand_source("unknown"), "unknown");
+    }
+
+    #[test]
+    fn test_with_default_options() {
+        let cmd = sample().with_default_options(CliOpts::command()).unwrap();
+        let matches = cmd
+            .try_get_matches_from(["ffizer", "apply", "-s", ".", "-d", "dst"])
+            .unwrap();
+        let crate::Command::Apply(opts) = CliOpts::from_arg_matches(&matches).unwrap().cmd else {
+            panic!("expected apply");
+        };
+        assert_eq!(opts.update_mode, crate::UpdateMode::Keep);
+        assert!(opts.no_interaction);
+        assert_eq!(opts.fetch.cache_ttl, 600);
+    }
+
+    #[test]
+    fn test_with_default_options_should_reject_unknown_option() {
+        let cfg = UserCfg {
+            defaults: BTreeMap::from([("update_mode".to_owned(), "keep".into())]),
+            ..Default::default()
+        };
+        assert!(matches!(
+            cfg.with_default_options(CliOpts::command()),
+            Err(Error::UnknownUserCfgOption { name }) if name == "update_mode"
+        ));
+    }
+}
@@ -6,10 +6,18 @@ pub struct VariableDef {
     pub default_value: Option<serde_yaml::Value>,
     /// sentence to ask the value (default to the name on variable)
     pub ask: Option<String>,
+    /// help text displayed under the prompt
+    pub help: Option<String>,
+    /// name of the group of related variables
+    pub group: Option<String>,
     /// is the variable hidden to the user (could be usefull to cache shared variable/data)
     pub hidden: bool,
     /// if non-empty then the value should selected into the list of value
     pub select_in_values: Vec<LabelValue>,
+    /// is the value a secret (masked and not persisted)
+    pub secret: bool,
+    /// is a value required (no fallback to an empty value)
+    pub required: bool,
 }
 
 #[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, Eq)]
@@ -1,38 +1,154 @@
 use crate::error::*;
 use serde::Serialize;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use tracing::instrument;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
-pub struct Variables(BTreeMap<String, serde_yaml::Value>);
+pub(crate) const MASKED_VALUE: &str = "********";
 
+#[derive(Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Variables {
+    values: BTreeMap<String, serde_yaml::Value>,
+    /// names of the variables whose value should never be displayed or persisted
+    #[serde(skip)]
+    secrets: BTreeSet<String>,
+}
+
+/// keys could be a path of names separated by `.` (eg `backend.name`) to access nested values (like in handlebars)
 impl Variables {
+    /// move the values of `v` into self (values from `v` win, and replace the previous ones)
     pub fn append(&mut self, v: &mut Variables) {
-        self.0.append(&mut v.0);
+        self.values.append(&mut v.values);
+        self.secrets.append(&mut v.secrets);
+    }
+
+    /// move the values of `v` into self, like `append` but the mappings of the `namespaces`
+    /// (eg `backend`) are merged instead of replaced, other mappings are still replaced
+    /// (eg the saved answers `backend.db` are kept when only `backend.name` is provided)
+    pub fn merge_nested(&mut self, v: &mut Variables, namespaces: &BTreeSet<String>) {
+        for (key, value) in std::mem::take(&mut v.values) {
+            match (self.values.get_mut(&key), value) {
+                (Some(serde_yaml::Value::Mapping(current)), serde_yaml::Value::Mapping(other))
+                    if namespaces.contains(&key) =>
+                {
+                    merge_mapping(current, other, &key, namespaces)
+                }
+                (_, value) => {
+                    self.values.insert(key, value);
+                }
+            }
+        }
+        self.secrets.append(&mut v.secrets);
     }
 
     pub fn insert<K: Into<String>, V: Serialize>(&mut self, key: K, value: V) -> Result<()> {
-        self.0.insert(key.into(), serde_yaml::to_value(value)?);
+        let key = key.into();
+        let value = serde_yaml::to_value(value)?;
+        self.values.insert(key, value);
         Ok(())
     }
 
+    /// insert the value at the path `key` (eg `backend.db.name` into the mapping `backend.db`),
+    /// fail if a parent is already a value (not a mapping)
+    pub fn insert_nested<K: Into<String>, V: Serialize>(&mut self, key: K, value: V) -> Result<()> {
+        let key = key.into();
+        let value = serde_yaml::to_value(value)?;
+        let Some((head, tail)) = key.split_once('.') else {
+            self.values.insert(key, value);
+            return Ok(());
+        };
+        let entry = self
+            .values
+            .entry(head.to_owned())
+            .or_insert_with(|| serde_yaml::Value::Mapping(Default::default()));
+        insert_nested(entry, head, tail, value)
+    }
+
     pub fn contains_key<K: Into<String>>(&self, key: K) -> bool {
-        self.0.contains_key(&key.into())
+        self.get(key).is_some()
     }
 
     pub fn tree(&self) -> &BTreeMap<String, serde_yaml::Value> {
-        &self.0
+        &self.values
     }
 
     pub fn get<K: Into<String>>(&self, key: K) -> Option<&serde_yaml::Value> {
-        self.0.get(&key.into())
+        let key = key.into();
+        if let Some(value) = self.values.get(&key) {
+            return Some(value);
+        }
+        let mut names = key.split('.');
+        let head = names.next()?;
+        names.try_fold(self.values.get(head)?, |v, name| v.get(name))
+    }
+
+    pub fn remove<K: Into<String>>(&mut self, key: K) -> Option<serde_yaml::Value> {
+        let key = key.into();
+        if let Some(value) = self.values.remove(&key) {
+            return Some(value);
+        }
+        match key.rsplit_once('.') {
+            None => self.values.remove(&key),
+            Some((parent, name)) => {
+                let mut names = parent.split('.');
+                let head = names.next()?;
+                names
+                    .try_fold(self.values.get_mut(head)?, |v, name| v.get_mut(name))?
+                    .as_mapping_mut()?
+                    .remove(name)
+            }
+        }
     }
 
     pub fn retain<F>(&mut self, f: F)
     where
         F: FnMut(&String, &mut serde_yaml::Value) -> bool,
     {
-        self.0.retain(f)
+        self.values.retain(f)
+    }
+
+    /// mark the variable (and its `__label`) as secret
+    pub fn mark_secret<K: Into<String>>(&mut self, key: K) {
+        let key = key.into();
+        self.secrets.insert(format!("{}__label", key));
+        self.secrets.insert(key);
+    }
+
+    /// remove the values of the secret variables
+    pub fn remove_secrets(&mut self) {
+        for key in self.secrets.clone() {
+            self.remove(key);
+        }
+    }
+
+    pub fn is_secret(&self, key: &str) -> bool {
+        self.secrets.contains(key)
+    }
+
+    /// replace the values of secret variables found into `text` by a mask
+    /// (to display the value of a known variable, prefer `mask_value`)
+    pub fn mask_secrets(&self, text: &str) -> String {
+        let mut out = text.to_owned();
+        for key in &self.secrets {
+            if let Some(value) = self
+                .get(key.as_str())
+                .and_then(|v| Self::value_as_str(v).ok())
+                && !value.is_empty()
+            {
+                out = out.replace(&value, MASKED_VALUE);
+            }
+        }
+        out
+    }
+
+    /// the value of the variable `key`, masked if the variable is a secret
+    pub fn mask_value(&self, key: &str, value: &str) -> String {
+        if self.is_secret(key) {
+            MASKED_VALUE.to_owned()
+        } else {
+            value.to_owned()
+        }
     }
 
     #[instrument]
@@ -48,6 +164,72 @@ impl Variables {
     }
 }
 
+fn insert_nested(
+    parent: &mut serde_yaml::Value,
+    parent_key: &str,
+    key: &str,
+    value: serde_yaml::Value,
+) -> Result<()> {
+    let Some(mapping) = parent.as_mapping_mut() else {
+        return Err(Error::VariableNotANamespace {
+            key: format!("{}.{}", parent_key, key),
+            parent: parent_key.to_owned(),
+        });
+    };
+    match key.split_once('.') {
+        None => {
+            mapping.insert(key.into(), value);
+            Ok(())
+        }
+        Some((head, tail)) => {
+            let entry = mapping
+                .entry(head.into())
+                .or_insert_with(|| serde_yaml::Value::Mapping(Default::default()));
+            insert_nested(entry, &format!("{}.{}", parent_key, head), tail, value)
+        }
+    }
+}
+
+/// merge `other` into `current` (the mapping at `path`), only the mappings of `namespaces` are merged
+fn merge_mapping(
+    current: &mut serde_yaml::Mapping,
+    other: serde_yaml::Mapping,
+    path: &str,
+    namespaces: &BTreeSet<String>,
+) {
+    for (key, value) in other {
+        let child_path = key.as_str().map(|k| format!("{}.{}", path, k));
+        match (current.get_mut(&key), value, child_path) {
+            (
+                Some(serde_yaml::Value::Mapping(c)),
+                serde_yaml::Value::Mapping(o),
+                Some(child_path),
+            ) if namespaces.contains(&child_path) => merge_mapping(c, o, &child_path, namespaces),
+            (_, value, _) => {
+                current.insert(key, value);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Variables {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut masked = self.clone();
+        for key in &self.secrets {
+            if self.values.contains_key(key) {
+                masked
+                    .insert(key.as_str(), MASKED_VALUE)
+                    .map_err(|_| fmt::Error)?;
+            } else if self.contains_key(key.as_str()) {
+                masked
+                    .insert_nested(key.as_str(), MASKED_VALUE)
+                    .map_err(|_| fmt::Error)?;
+            }
+        }
+        f.debug_map().entries(masked.values.iter()).finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -76,4 +258,126 @@ mod tests {
             &Variables::value_from_str("42").unwrap()
         );
     }
+
+    #[test]
+    fn test_nested_keys() {
+        let mut variables = Variables::default();
+        variables.insert("prj", "myprj").unwrap();
+        variables.insert_nested("backend.name", "api").unwrap();
+        variables.insert_nested("backend.db.kind", "pg").unwrap();
+        assert_eq!(
+            Some(&serde_yaml::Value::String("api".to_owned())),
+            variables.get("backend.name")
+        );
+        assert_eq!(true, variables.contains_key("backend.db.kind"));
+        assert_eq!(false, variables.contains_key("backend.version"));
+        assert_eq!(false, variables.contains_key("prj.name"));
+
+        let mut others = Variables::default();
+        others.insert_nested("backend.version", "1.0").unwrap();
+        others.insert_nested("backend.name", "service").unwrap();
+        variables.merge_nested(&mut others, &BTreeSet::from(["backend".to_owned()]));
+        assert_eq!(
+            Some(&serde_yaml::Value::String("service".to_owned())),
+            variables.get("backend.name")
+        );
+        assert_eq!(true, variables.contains_key("backend.version"));
+        assert_eq!(true, variables.contains_key("backend.db.kind"));
+
+        assert_eq!(
+            Some(serde_yaml::Value::String("pg".to_owned())),
+            variables.remove("backend.db.kind")
+        );
+        assert_eq!(false, variables.contains_key("backend.db.kind"));
+        assert_eq!(None, variables.remove("frontend.name"));
+    }
+
+    #[test]
+    fn test_append_should_replace_mappings() {
+        let mut variables = Variables::default();
+        variables
+            .insert("tags", BTreeMap::from([("a", 1), ("b", 2)]))
+            .unwrap();
+        let mut others = Variables::default();
+        others.insert("tags", BTreeMap::from([("c", 3)])).unwrap();
+        variables.append(&mut others);
+        assert_eq!(false, variables.contains_key("tags.a"));
+        assert_eq!(true, variables.contains_key("tags.c"));
+
+        // only the mappings of namespaces are merged
+        let mut others = Variables::default();
+        others.insert("tags", BTreeMap::from([("d", 4)])).unwrap();
+        others.insert_nested("backend.name", "api").unwrap();
+        variables.merge_nested(&mut others, &BTreeSet::from(["backend".to_owned()]));
+        assert_eq!(false, variables.contains_key("tags.c"));
+        assert_eq!(true, variables.contains_key("tags.d"));
+    }
+
+    #[test]
+    fn test_insert_is_flat() {
+        let mut variables = Variables::default();
+        variables.insert("a.b", "x").unwrap();
+        assert_eq!(
+            vec!["a.b"],
+            variables
+                .tree()
+                .keys()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Some(&serde_yaml::Value::String("x".to_owned())),
+            variables.get("a.b")
+        );
+        assert_eq!(
+            Some(serde_yaml::Value::String("x".to_owned())),
+            variables.remove("a.b")
+        );
+    }
+
+    #[test]
+    fn test_insert_nested_should_not_replace_value() {
+        let mut variables = Variables::default();
+        variables.insert("backend", "api").unwrap();
+        assert!(matches!(
+            variables.insert_nested("backend.db.name", "pg"),
+            Err(Error::VariableNotANamespace { parent, .. }) if parent == "backend"
+        ));
+        assert_eq!(
+            Some(&serde_yaml::Value::String("api".to_owned())),
+            variables.get("backend")
+        );
+    }
+
+    #[test]
+    fn test_secret_are_masked() {
+        let mut variables = Variables::default();
+        variables.insert("prj", "myprj").unwrap();
+        variables.insert("token", "s3cr3t").unwrap();
+        variables.mark_secret("token");
+        let debug = format!("{:?}", variables);
+        assert_eq!(true, debug.contains("myprj"));
+        assert_eq!(false, debug.contains("s3cr3t"));
+        assert_eq!(
+            "myprj/********.txt",
+            variables.mask_secrets("myprj/s3cr3t.txt")
+        );
+        variables
+            .insert_nested("backend.password", "p4ssw0rd")
+            .unwrap();
+        variables.mark_secret("backend.password");
+        let debug = format!("{:?}", variables);
+        assert_eq!(false, debug.contains("p4ssw0rd"));
+        assert_eq!("********", variables.mask_secrets("p4ssw0rd"));
+    }
+
+    #[test]
+    fn test_short_secret_are_masked() {
+        let mut variables = Variables::default();
+        variables.insert("pin", "ab").unwrap();
+        variables.mark_secret("pin");
+        assert_eq!("pin: ********", variables.mask_secrets("pin: ab"));
+        assert_eq!("********", variables.mask_value("pin", "ab"));
+        assert_eq!("ab", variables.mask_value("prj", "ab"));
+    }
 }
@@ -350,3 +350,867 @@ fn log_should_report_error() -> Result<(), Box<dyn Error>> {
         .failure();
     Ok(())
 }
+
+#[test]
+fn apply_without_interaction_should_fail_on_missing_required_variables()
+-> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let template_path = PathBuf::from("tests/data/required_variables");
+    let actual_path = tmp_dir.path().join("my-project");
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(template_path.to_str().unwrap())
+        .assert()
+        .stderr(
+            predicate::str::contains("`--variables k1=<value>`")
+                .and(predicate::str::contains("k2=").not())
+                .and(predicate::str::contains("k3=").not()),
+        )
+        .failure();
+    assert_eq!(actual_path.join("file1.txt").exists(), false);
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(template_path.to_str().unwrap())
+        .arg("-v")
+        .arg("k1=v1")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("file1.txt"))?,
+        "k1: v1\nk2: d2\nk3: \n"
+    );
+    Ok(())
+}
+
+#[test]
+fn apply_should_use_variables_from_environment() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let template_path = PathBuf::from("tests/data/env_variables");
+    let actual_path = tmp_dir.path().join("my-project");
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(template_path.to_str().unwrap())
+        .env("FFIZER_VAR_PROJECT_NAME", "p1")
+        .env("FFIZER_TEST_AUTHOR", "alice")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("file1.txt"))?,
+        "project_name: p1\nauthor: alice\n"
+    );
+
+    // cli overrides environment
+    let actual_path = tmp_dir.path().join("my-project-2");
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(template_path.to_str().unwrap())
+        .arg("-v")
+        .arg("project_name=p2")
+        .env("FFIZER_VAR_PROJECT_NAME", "p1")
+        .env_remove("FFIZER_TEST_AUTHOR")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("file1.txt"))?,
+        "project_name: p2\nauthor: nobody\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn apply_should_not_review_when_every_value_is_provided() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let template_path = PathBuf::from("tests/data/env_variables");
+    let actual_path = tmp_dir.path().join("my-project");
+
+    // interactive mode, but without terminal: any prompt would fail
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("apply")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(template_path.to_str().unwrap())
+        .arg("-v")
+        .arg("project_name=p1")
+        .arg("-v")
+        .arg("author=bob")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("file1.txt"))?,
+        "project_name: p1\nauthor: bob\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn apply_should_fail_on_ambiguous_variable_from_environment() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let template_path = PathBuf::from("tests/data/env_variables_ambiguous");
+    let actual_path = tmp_dir.path().join("my-project");
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(template_path.to_str().unwrap())
+        .env("FFIZER_VAR_PROJECT_NAME", "p1")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "environment variable 'FFIZER_VAR_PROJECT_NAME' is ambiguous",
+        ));
+    assert!(!actual_path.join("file1.txt").exists());
+    Ok(())
+}
+
+#[test]
+fn apply_should_use_values_from_preset() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let template_path = PathBuf::from("tests/data/presets");
+    let actual_path = tmp_dir.path().join("my-project");
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(template_path.to_str().unwrap())
+        .arg("--preset")
+        .arg("unknown")
+        .assert()
+        .stderr(predicate::str::contains(
+            "\"unknown\" of preset is not in [\"service\"]",
+        ))
+        .failure();
+
+    // cli overrides preset
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(template_path.to_str().unwrap())
+        .arg("--preset")
+        .arg("service")
+        .arg("-v")
+        .arg("with_ci=false")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("file1.txt"))?,
+        "kind: bin\nwith_ci: false\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn reapply_should_keep_answers_over_preset() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let template_path = PathBuf::from("tests/data/presets");
+    let actual_path = tmp_dir.path().join("my-project");
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(template_path.to_str().unwrap())
+        .arg("--preset")
+        .arg("service")
+        .arg("-v")
+        .arg("with_ci=false")
+        .assert()
+        .success();
+    fs::remove_file(actual_path.join("file1.txt"))?;
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("reapply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("file1.txt"))?,
+        "kind: bin\nwith_ci: false\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn apply_should_override_variables_of_imported_templates() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let template_path = PathBuf::from("tests/data/import_overrides/parent");
+    let actual_path = tmp_dir.path().join("my-project");
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(template_path.to_str().unwrap())
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("child.txt"))?,
+        "project: child-project\nlicense: MIT\n"
+    );
+    assert_eq!(
+        fs::read_to_string(actual_path.join("grandchild.txt"))?,
+        "author: parent-author\nyear: 2000\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn reapply_should_keep_values_fixed_by_imports() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let template_path = PathBuf::from("tests/data/import_overrides/parent");
+    let actual_path = tmp_dir.path().join("my-project");
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(template_path.to_str().unwrap())
+        .assert()
+        .success();
+    // save an other answer for the variable fixed by the import
+    let options_path = actual_path.join(".ffizer/options.yaml");
+    let options = fs::read_to_string(&options_path)?;
+    assert!(options.contains("default_value: MIT"));
+    fs::write(
+        &options_path,
+        options.replace("default_value: MIT", "default_value: Apache-2.0"),
+    )?;
+    fs::remove_file(actual_path.join("child.txt"))?;
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .env("FFIZER_VAR_LICENSE", "Apache-2.0")
+        .arg("reapply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("child.txt"))?,
+        "project: child-project\nlicense: MIT\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn apply_should_scope_variables_of_imported_templates() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let template_path = PathBuf::from("tests/data/namespaces/parent");
+    let actual_path = tmp_dir.path().join("my-project");
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(template_path.to_str().unwrap())
+        .arg("-v")
+        .arg("a.name=x")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("a.txt"))?,
+        "name: x\nversion: 0.1.0\n"
+    );
+    assert_eq!(
+        fs::read_to_string(actual_path.join("b.txt"))?,
+        "name: b-name\nversion: 1.0.0\n"
+    );
+    let options = fs::read_to_string(actual_path.join(".ffizer/options.yaml"))?;
+    assert!(options.contains("- name: a\n  default_value:\n    name: x\n"));
+    Ok(())
+}
+
+#[test]
+fn apply_should_import_templates_only_if_enabled() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let template_path = PathBuf::from("tests/data/conditional_imports/parent");
+    let actual_path = tmp_dir.path().join("my-project");
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(template_path.to_str().unwrap())
+        .arg("-v")
+        .arg("with_ci=true")
+        .arg("-v")
+        .arg("base_image=debian")
+        .assert()
+        .success();
+    assert!(actual_path.join("ci.yml").exists());
+    assert_eq!(
+        fs::read_to_string(actual_path.join("Dockerfile"))?,
+        "FROM debian\n"
+    );
+
+    let actual_path = tmp_dir.path().join("my-project-2");
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(template_path.to_str().unwrap())
+        .arg("-v")
+        .arg("with_docker=false")
+        .assert()
+        .success();
+    assert!(!actual_path.join("ci.yml").exists());
+    assert!(!actual_path.join("Dockerfile").exists());
+    assert!(actual_path.join("README.md").exists());
+    Ok(())
+}
+
+#[test]
+fn apply_should_put_imported_template_into_dst_subfolder() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let template_path = PathBuf::from("tests/data/dst_subfolder/parent");
+    let actual_path = tmp_dir.path().join("my-project");
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(template_path.to_str().unwrap())
+        .arg("-v")
+        .arg("name=bar")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("Cargo.toml"))?,
+        "members = [\"crates/bar\"]\n"
+    );
+    assert_eq!(
+        fs::read_to_string(actual_path.join("crates/bar/Cargo.toml"))?,
+        "name = \"bar\"\n"
+    );
+    assert!(actual_path.join("crates/bar/src/main.rs").exists());
+
+    // reapply uses the saved answers to find the same subfolder
+    fs::remove_file(actual_path.join("crates/bar/Cargo.toml"))?;
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("reapply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--update-mode")
+        .arg("keep")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("crates/bar/Cargo.toml"))?,
+        "name = \"bar\"\n"
+    );
+    assert!(!actual_path.join("crates/foo").exists());
+    Ok(())
+}
+
+#[test]
+fn apply_should_ignore_and_rename_files_of_imported_templates() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let template_path = PathBuf::from("tests/data/import_files/parent");
+    let actual_path = tmp_dir.path().join("my-project");
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(template_path.to_str().unwrap())
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("README.md"))?,
+        "parent\n"
+    );
+    assert_eq!(
+        fs::read_to_string(actual_path.join("docs/base.md"))?,
+        "base\n"
+    );
+    assert!(actual_path.join("main.rs").exists());
+    assert!(actual_path.join(".github/workflows/build.yml").exists());
+    assert!(!actual_path.join("ci").exists());
+    assert!(!actual_path.join("LICENSE").exists());
+    Ok(())
+}
+
+#[test]
+fn apply_should_report_cycle_into_imports() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let template_path = PathBuf::from("tests/data/imports_cycle/a");
+    let actual_path = tmp_dir.path().join("my-project");
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(template_path.to_str().unwrap())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "cycle into the imports of templates: tests/data/imports_cycle/a (rev: 'master') -> tests/data/imports_cycle/a/../b -> tests/data/imports_cycle/a/../b/../c -> tests/data/imports_cycle/a/../b/../c/../a",
+        ));
+    Ok(())
+}
+
+#[test]
+fn show_imports_should_print_the_tree_of_imports() -> Result<(), Box<dyn Error>> {
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .arg("show-imports")
+        .arg("--source")
+        .arg("tests/data/imports_tree/root")
+        .arg("-v")
+        .arg("base=tests/data/imports_tree")
+        .assert()
+        .success()
+        .stdout(predicate::str::diff(
+            r#"[0] tests/data/imports_tree/root (rev: 'master')
+ ├─[1] tests/data/imports_tree/x
+ │  └─[3] tests/data/imports_tree/z
+ └─[2] tests/data/imports_tree/y
+    └─[3] tests/data/imports_tree/z (already imported)
+"#,
+        ));
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn apply_should_use_cached_template_when_fetch_fails() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let cache_home = tmp_dir.path().join("cache");
+    // the `.invalid` domain never resolves, so the fetch always fails
+    let uri = "https://invalid.invalid/ffizer/cached_template.git";
+    let cached_path = cache_home.join("ffizer/git/invalid.invalid/ffizer/cached_template/master");
+    fs::create_dir_all(&cached_path)?;
+    fs::write(cached_path.join("file_1.txt"), "cached\n")?;
+    let (code, output, error) = run_script::run(
+        &format!(
+            r#"
+                cd {cached_path:?}
+                git init -q -b master
+                git config user.email "test@example.com"
+                git config user.name "Test Name"
+                git remote add origin {uri}
+                git add file_1.txt
+                git commit -q -m "add file_1.txt"
+                "#
+        ),
+        &vec![],
+        &run_script::ScriptOptions::new(),
+    )?;
+    assert_eq!(code, 0, "setup cached template: {output} {error}");
+
+    let actual_path = tmp_dir.path().join("my-project");
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .env("XDG_CACHE_HOME", &cache_home)
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--refresh")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(uri)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("use the previous cached checkout"));
+    assert_eq!(
+        fs::read_to_string(actual_path.join("file_1.txt"))?,
+        "cached\n"
+    );
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .env("XDG_CACHE_HOME", &cache_home)
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--refresh")
+        .arg("--strict-fetch")
+        .arg("--destination")
+        .arg(tmp_dir.path().join("my-project-2").to_str().unwrap())
+        .arg("--source")
+        .arg(uri)
+        .assert()
+        .failure();
+    assert!(cached_path.join("file_1.txt").exists());
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn cache_clear_should_remove_only_the_revs_of_an_absolute_path_uri() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let cache_home = tmp_dir.path().join("cache");
+    let cached_path = cache_home.join("ffizer/git/host/srv/git/tmpl/master");
+    let other_path = cache_home.join("ffizer/git/host/team/other/master");
+    for path in [&cached_path, &other_path] {
+        fs::create_dir_all(path)?;
+        let (code, output, error) = run_script::run(
+            &format!(
+                r#"
+                cd {path:?}
+                git init -q -b master
+                git config user.email "test@example.com"
+                git config user.name "Test Name"
+                echo "cached" > file_1.txt
+                git add file_1.txt
+                git commit -q -m "cached"
+                "#
+            ),
+            &vec![],
+            &run_script::ScriptOptions::new(),
+        )?;
+        assert_eq!(code, 0, "setup cached template: {output} {error}");
+    }
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .env("XDG_CACHE_HOME", &cache_home)
+        .arg("cache")
+        .arg("clear")
+        .arg("git@host:/srv/git/tmpl")
+        .assert()
+        .success();
+    assert!(!cached_path.exists());
+    assert!(other_path.join("file_1.txt").exists());
+    Ok(())
+}
+
+#[test]
+fn apply_should_fetch_template_from_mirror() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let mirror_path = tmp_dir.path().join("mirror");
+    let src_path = tmp_dir.path().join("src");
+    fs::create_dir_all(&src_path)?;
+    // a local bare repository as mirror of `https://invalid.invalid/`
+    let (code, output, error) = run_script::run(
+        &format!(
+            r#"
+                cd {src_path:?}
+                git init -q -b master
+                git config user.email "test@example.com"
+                git config user.name "Test Name"
+                echo "from mirror" > file_1.txt
+                git add file_1.txt
+                git commit -q -m "add file_1.txt"
+                git clone -q --bare {src_path:?} {:?}
+                "#,
+            mirror_path.join("ffizer/mirrored_template")
+        ),
+        &vec![],
+        &run_script::ScriptOptions::new(),
+    )?;
+    assert_eq!(code, 0, "setup mirror: {output} {error}");
+    let home = tmp_dir.path().join("home");
+    fs::create_dir_all(&home)?;
+    let mirror = format!("{}/", mirror_path.to_str().unwrap());
+
+    // with FFIZER_GIT_MIRROR
+    let actual_path = tmp_dir.path().join("my-project");
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("XDG_CACHE_HOME", tmp_dir.path().join("cache"))
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env(
+            "FFIZER_GIT_MIRROR",
+            format!("https://invalid.invalid/={mirror}"),
+        )
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg("https://invalid.invalid/ffizer/mirrored_template.git")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("file_1.txt"))?,
+        "from mirror\n"
+    );
+
+    // with the `insteadOf` of the git configuration
+    fs::write(
+        home.join(".gitconfig"),
+        format!("[url \"{mirror}\"]\n\tinsteadOf = https://other.invalid/\n"),
+    )?;
+    let actual_path = tmp_dir.path().join("my-project-2");
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .env("HOME", &home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("XDG_CACHE_HOME", tmp_dir.path().join("cache"))
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env_remove("FFIZER_GIT_MIRROR")
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg("https://other.invalid/ffizer/mirrored_template.git")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("file_1.txt"))?,
+        "from mirror\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn apply_should_use_archive_as_template() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let archive_path = tmp_dir.path().join("my_template-1.0.0.tar.gz");
+    // release artifacts usually wrap the files into a top folder
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        fs::File::create(&archive_path)?,
+        flate2::Compression::default(),
+    ));
+    builder.append_dir_all("my_template-1.0.0", "tests/data/dst_subfolder/child")?;
+    builder.into_inner()?.finish()?;
+    let actual_path = tmp_dir.path().join("my-project");
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .env("XDG_CACHE_HOME", tmp_dir.path().join("cache"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(format!("file://{}", archive_path.to_str().unwrap()))
+        .arg("--source-subfolder")
+        .arg("my_template-1.0.0")
+        .arg("-v")
+        .arg("name=bar")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("Cargo.toml"))?,
+        "name = \"bar\"\n"
+    );
+    assert!(actual_path.join("src/main.rs").exists());
+
+    // same with the subfolder inline into the source
+    let actual_path = tmp_dir.path().join("my-project-inline");
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .env("XDG_CACHE_HOME", tmp_dir.path().join("cache"))
+        .arg("apply")
+        .arg("--no-interaction")
+        .arg("--confirm")
+        .arg("never")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg(format!(
+            "file://{}//my_template-1.0.0",
+            archive_path.to_str().unwrap()
+        ))
+        .arg("-v")
+        .arg("name=bar")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("Cargo.toml"))?,
+        "name = \"bar\"\n"
+    );
+
+    // the extracted archive is managed like the other cached templates
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .env("XDG_CACHE_HOME", tmp_dir.path().join("cache"))
+        .arg("cache")
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "archive/my_template-1.0.0.tar.gz-",
+        ));
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .env("XDG_CACHE_HOME", tmp_dir.path().join("cache"))
+        .arg("cache")
+        .arg("clear")
+        .arg(archive_path.to_str().unwrap())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 cached template(s) removed"));
+    assert!(!tmp_dir.path().join("cache/ffizer/archive").exists());
+    Ok(())
+}
+
+#[test]
+fn apply_should_use_user_cfg() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let template_path = fs::canonicalize("tests/data/env_variables")?;
+    let cfg_path = tmp_dir.path().join("config.yaml");
+    fs::write(
+        &cfg_path,
+        format!(
+            "aliases:\n  env-tmpl: {:?}\ndefaults:\n  no-interaction: true\n  confirm: never\nvariables:\n  project_name: p3\n  author: bob\n",
+            template_path.to_str().unwrap()
+        ),
+    )?;
+    let actual_path = tmp_dir.path().join("my-project");
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .env("FFIZER_CONFIG", &cfg_path)
+        .env_remove("FFIZER_TEST_AUTHOR")
+        .arg("apply")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg("env-tmpl")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("file1.txt"))?,
+        "project_name: p3\nauthor: bob\n"
+    );
+
+    // an existing local folder wins over the alias with the same name
+    let local_path = tmp_dir.path().join("env-tmpl");
+    fs::create_dir_all(&local_path)?;
+    fs::write(local_path.join("local.txt"), "local\n")?;
+    let actual_path = tmp_dir.path().join("my-local-project");
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .env("FFIZER_CONFIG", &cfg_path)
+        .current_dir(tmp_dir.path())
+        .arg("apply")
+        .arg("--destination")
+        .arg(actual_path.to_str().unwrap())
+        .arg("--source")
+        .arg("env-tmpl")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(actual_path.join("local.txt"))?,
+        "local\n"
+    );
+    assert!(!actual_path.join("file1.txt").exists());
+    Ok(())
+}
+
+#[test]
+fn apply_should_fail_on_unknown_option_of_user_cfg() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let cfg_path = tmp_dir.path().join("config.yaml");
+    fs::write(&cfg_path, "defaults:\n  no_interaction: true\n")?;
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .env("FFIZER_CONFIG", &cfg_path)
+        .arg("apply")
+        .arg("--destination")
+        .arg(tmp_dir.path().join("my-project").to_str().unwrap())
+        .arg("--source")
+        .arg("tests/data/template_1")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no_interaction"));
+    Ok(())
+}
+
+#[test]
+fn inspect_and_help_should_warn_on_invalid_user_cfg() -> Result<(), Box<dyn Error>> {
+    let tmp_dir = tempdir()?;
+    let cfg_path = tmp_dir.path().join("config.yaml");
+    fs::write(&cfg_path, "defaults: [not, a, mapping\n")?;
+
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .env("FFIZER_CONFIG", &cfg_path)
+        .arg("inspect")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("user configuration:"))
+        .stderr(predicate::str::contains("warning"));
+    cargo_bin_cmd!(env!("CARGO_PKG_NAME"))
+        .env("FFIZER_CONFIG", &cfg_path)
+        .arg("apply")
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--destination"));
+    Ok(())
+}